@@ -1,35 +1,65 @@
 use anyhow::Result;
-use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
-use crossterm::event::{self, Event, KeyCode};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone};
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
 use mvg_api::routes::Connection;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::Mutex;
-use tui::{backend::Backend, widgets::TableState, Terminal};
+use tui::{
+    backend::Backend,
+    widgets::{ListState, TableState},
+    Terminal,
+};
 
-use crate::networking::{start_tokio, IoEvent, RoutesParams};
-use crate::ui::ui;
+use crate::clipboard::copy_to_clipboard;
+use crate::export::{connection_summary, connection_to_ics, ics_file_name};
+use crate::gps::{DEFAULT_GPSD_ADDRESS, HERE};
+use crate::itinerary::{parse_via, Stop};
+use crate::keymap::{Action, Keymap};
+use crate::messages::{Message, Severity, MAX_TOASTS};
+use crate::modes::{default_modes, transport_types, MODES};
+use crate::networking::{start_tokio, Disruption, IoEvent, NearbyStation, RoutesParams};
+use crate::routes_view::{matches_filter, sort_routes, Avoid, Preferences, SortKey};
+use crate::theme::Theme;
+use crate::ui::{ui, LayoutMode};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
     Editing,
     Table,
 }
 
-#[derive(Debug)]
+/// The mode toggles from this index on are above the details pane and below
+/// the destination in the wide layout, the ones before it above the routes
+/// table and below the via field. The toggles take the right two thirds of the
+/// row and the details the right 30 %, so it is about the second half.
+const FIRST_MODE_ABOVE_DETAILS: usize = MODES.len().div_ceil(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Focus {
     Start,
+    Via,
     Destination,
     Date,
     Time,
     Arrival,
-    Ubahn,
-    Sbahn,
-    Tram,
-    Bus,
+    /// Toggle of the mode at this index of `MODES`
+    Mode(usize),
     Routes,
+    Details,
+    Notifications,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailsView {
+    List,
+    Timeline,
+    Map,
+    /// All parts of a trip planned over via stops
+    Plan,
 }
 
 #[derive(Debug)]
@@ -37,24 +67,70 @@ pub struct App {
     pub input_mode: InputMode,
     pub focus: Focus,
     pub input_start: String,
+    pub input_via: String,
     pub input_destination: String,
     pub start: String,
+    /// Stops between start and destination, parsed from `input_via`
+    pub via: Vec<Stop>,
     pub destination: String,
+    /// Via stops of the planned trip in `routes`, empty for plain connections
+    pub plan: Vec<Stop>,
+    /// Connections shown in the routes table, filtered and sorted
     pub routes: Vec<Connection>,
-    pub messages: Vec<String>,
+    /// Fetched connections hidden by the filter
+    pub filtered_routes: Vec<Connection>,
+    pub sort_key: SortKey,
+    pub sort_descending: bool,
+    pub filter: String,
+    /// Typing goes to the filter instead of the focused input
+    pub editing_filter: bool,
+    /// Log of the session, the latest messages are also shown as toasts
+    pub messages: Vec<Message>,
     pub show_fetch_popup: bool,
     io_tx: Option<Sender<IoEvent>>,
     pub frames: i64,
+    pub should_quit: bool,
+    pub show_help: bool,
+    pub help_scroll: u16,
+    pub show_log: bool,
+    pub log_scroll: u16,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub layout: LayoutMode,
+    /// Time the UI is rendered at, used for relative times like the `IN` column
+    pub now: DateTime<Local>,
+    /// Time zone the times are shown in, the local one if `None`
+    pub time_zone: Option<FixedOffset>,
     pub datetime: DateTime<Local>,
     pub input_date: String,
     pub input_time: String,
     pub wrong_time: bool,
     pub wrong_date: bool,
+    pub wrong_via: bool,
     pub is_arrival: bool,
-    pub use_ubahn: bool,
-    pub use_sbahn: bool,
-    pub use_tram: bool,
-    pub use_bus: bool,
+    /// Whether each mode of `MODES` is included in the routes
+    pub modes: Vec<bool>,
+    /// Lines and stations left out of the fetched connections
+    pub avoid: Avoid,
+    pub preferences: Preferences,
+    /// Address of gpsd for the `here` pseudo-station
+    pub gpsd: String,
+    /// Stations near `nearby_location`, the nearest first
+    pub nearby: Vec<NearbyStation>,
+    pub nearby_location: String,
+    pub show_nearby: bool,
+    pub nearby_selected: usize,
+    /// Show the departures of the selected nearby station instead of the list
+    pub nearby_board: bool,
+    /// Current service messages of the MVG, fetched with the routes
+    pub disruptions: Vec<Disruption>,
+    pub show_disruptions: bool,
+    pub disruptions_scroll: u16,
+    pub details_view: DetailsView,
+    /// Show only the focused routes, details or notifications pane
+    pub maximized: bool,
+    pub map_zoom: f64,
+    pub map_pan: (f64, f64),
 }
 
 impl Default for App {
@@ -63,28 +139,67 @@ impl Default for App {
             input_mode: InputMode::Normal,
             focus: Focus::Start,
             input_start: String::new(),
+            input_via: String::new(),
             input_destination: String::new(),
             start: String::new(),
+            via: Vec::new(),
             destination: String::new(),
+            plan: Vec::new(),
             routes: Vec::new(),
+            filtered_routes: Vec::new(),
+            sort_key: SortKey::default(),
+            sort_descending: false,
+            filter: String::new(),
+            editing_filter: false,
             messages: Vec::new(),
             show_fetch_popup: false,
             io_tx: None,
             frames: 0,
+            should_quit: false,
+            show_help: false,
+            help_scroll: 0,
+            show_log: false,
+            log_scroll: 0,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            layout: LayoutMode::default(),
+            now: Local::now(),
+            time_zone: None,
             datetime: Local::now(),
             input_date: Local::now().format("%d.%m.%Y").to_string(),
             input_time: Local::now().format("%H:%M").to_string(),
             wrong_time: false,
             wrong_date: false,
+            wrong_via: false,
             is_arrival: false,
-            use_ubahn: true,
-            use_sbahn: true,
-            use_tram: true,
-            use_bus: true,
+            modes: default_modes(),
+            avoid: Avoid::default(),
+            preferences: Preferences::default(),
+            gpsd: DEFAULT_GPSD_ADDRESS.to_string(),
+            nearby: Vec::new(),
+            nearby_location: String::new(),
+            show_nearby: false,
+            nearby_selected: 0,
+            nearby_board: false,
+            disruptions: Vec::new(),
+            show_disruptions: false,
+            disruptions_scroll: 0,
+            details_view: DetailsView::List,
+            maximized: false,
+            map_zoom: 1.0,
+            map_pan: (0.0, 0.0),
         }
     }
 }
 
+/// `time` as shown in `time_zone`, or in the local time zone if it is `None`.
+pub fn shown_time(time: &DateTime<Local>, time_zone: Option<FixedOffset>) -> DateTime<FixedOffset> {
+    match time_zone {
+        Some(offset) => time.with_timezone(&offset),
+        None => time.with_timezone(time.offset()),
+    }
+}
+
 impl App {
     pub fn new(io_tx: Sender<IoEvent>) -> Self {
         App {
@@ -93,104 +208,430 @@ impl App {
         }
     }
 
-    async fn handle_fetch(&mut self) {
-        if self.wrong_time || self.wrong_date {
+    fn handle_fetch(&mut self) {
+        if self.wrong_time || self.wrong_date || self.wrong_via {
+            return;
+        }
+        let rp = RoutesParams {
+            from: self.start.to_string(),
+            to: self.destination.to_string(),
+            time: self.datetime,
+            arrival: self.is_arrival,
+            transport_types: transport_types(&self.modes),
+            avoid: self.avoid.clone(),
+            preferences: self.preferences.clone(),
+            gpsd: self.gpsd.clone(),
+        };
+        match self.via.is_empty() {
+            true => self.send_io(IoEvent::GetRoutes(rp)),
+            false => self.send_io(IoEvent::PlanTrip(rp, self.via.clone())),
+        }
+    }
+
+    /// Hands `event` to the network task and shows the fetch popup until it is
+    /// done. Without a network task nothing is fetched.
+    fn send_io(&mut self, event: IoEvent) {
+        let sent = match &self.io_tx {
+            Some(tx) => tx.try_send(event),
+            None => return,
+        };
+        match sent {
+            Ok(()) => self.show_fetch_popup = true,
+            Err(e) => self.log(Severity::Error, format!("Could not start fetching: {}", e)),
+        }
+    }
+
+    /// Handles a single key press, depending on the current input mode and the keymap.
+    pub fn handle_key(&mut self, key: KeyEvent, routes_table_state: &mut RoutesTableState) {
+        if self.show_help || self.show_log || self.show_disruptions {
+            // The help is drawn on top of the others
+            let scroll = match (self.show_help, self.show_log) {
+                (true, _) => &mut self.help_scroll,
+                (false, true) => &mut self.log_scroll,
+                (false, false) => &mut self.disruptions_scroll,
+            };
+            match self.keymap.help_action(key.code) {
+                Some(Action::ScrollDown) => *scroll = scroll.saturating_add(1),
+                Some(Action::ScrollUp) => *scroll = scroll.saturating_sub(1),
+                Some(Action::Leave) => {
+                    self.show_help = false;
+                    self.show_log = false;
+                    self.show_disruptions = false;
+                }
+                _ => {}
+            }
+            return;
+        }
+        if self.show_nearby {
+            self.handle_nearby_key(key.code);
             return;
         }
-        self.show_fetch_popup = true;
-        if let Some(tx) = &self.io_tx {
-            _ = tx
-                .send(IoEvent::GetRoutes(RoutesParams {
-                    from: self.start.to_string(),
-                    to: self.destination.to_string(),
-                    time: self.datetime,
-                    arrival: self.is_arrival,
-                    include_ubahn: self.use_ubahn,
-                    include_bus: self.use_bus,
-                    include_tram: self.use_tram,
-                    include_sbahn: self.use_sbahn,
-                }))
-                .await;
+        let action = self.keymap.action(self.input_mode, key.code);
+        match self.input_mode {
+            InputMode::Normal => match action {
+                Some(Action::Quit) => self.should_quit = true,
+                Some(Action::Activate) => self.handle_i_key(),
+                Some(Action::FocusLeft) => self.handle_h_key(),
+                Some(Action::FocusRight) => self.handle_l_key(),
+                Some(Action::FocusDown) => self.handle_j_key(),
+                Some(Action::FocusUp) => self.handle_k_key(),
+                Some(Action::ShowHelp) => self.show_help = true,
+                Some(Action::Fetch) => self.handle_fetch(),
+                Some(Action::ToggleArrival) => self.is_arrival = !self.is_arrival,
+                Some(Action::ToggleDetailsView) => self.toggle_details_view(),
+                Some(Action::Maximize) => self.maximized = !self.maximized,
+                Some(Action::ShowLog) => self.open_log(),
+                Some(Action::Filter) => self.start_filter(),
+                Some(Action::ShowNearby) => self.fetch_nearby(),
+                Some(Action::ShowDisruptions) => self.fetch_disruptions(),
+                _ => {}
+            },
+            InputMode::Editing if self.editing_filter => match (action, key.code) {
+                (Some(Action::DeleteChar), _) => {
+                    self.filter.pop();
+                    self.apply_routes_view();
+                    routes_table_state.view_changed(self.routes.len());
+                }
+                (Some(Action::Leave), _) => {
+                    self.editing_filter = false;
+                    self.input_mode = InputMode::Table;
+                }
+                (_, KeyCode::Char(c)) => {
+                    self.filter.push(c);
+                    self.apply_routes_view();
+                    routes_table_state.view_changed(self.routes.len());
+                }
+                _ => {}
+            },
+            InputMode::Editing => match (action, key.code) {
+                (Some(Action::DeleteChar), _) => self.handle_backspace(),
+                (Some(Action::Leave), _) => self.handle_esc(),
+                (_, KeyCode::Char(c)) => self.handle_typing(c),
+                _ => {}
+            },
+            InputMode::Table => {
+                let selected = routes_table_state.table_state.selected();
+                let state = routes_table_state;
+                match (action, self.focus) {
+                    (Some(Action::NextEntry), Focus::Details) => state.next_details_line(self),
+                    (Some(Action::PreviousEntry), Focus::Details) => state.previous_details_line(),
+                    (Some(Action::ToggleStops), Focus::Details) => state.toggle_stops(self),
+                    (Some(Action::NextEntry), Focus::Notifications) => {
+                        state.notifications_scroll += 1
+                    }
+                    (Some(Action::PreviousEntry), Focus::Notifications) => {
+                        state.notifications_scroll = state.notifications_scroll.saturating_sub(1)
+                    }
+                    (Some(Action::NextEntry), _) => state.next_table_entry(self),
+                    (Some(Action::PreviousEntry), _) => state.previous_table_entry(self),
+                    (Some(Action::ToggleDetailsView), _) => self.toggle_details_view(),
+                    (Some(Action::ZoomIn), _) => self.zoom_map(1.5),
+                    (Some(Action::ZoomOut), _) => self.zoom_map(1.0 / 1.5),
+                    (Some(Action::PanLeft), _) => self.pan_map(-0.1, 0.0),
+                    (Some(Action::PanRight), _) => self.pan_map(0.1, 0.0),
+                    (Some(Action::PanDown), _) => self.pan_map(0.0, -0.1),
+                    (Some(Action::PanUp), _) => self.pan_map(0.0, 0.1),
+                    (Some(Action::ResetMap), _) => self.reset_map(),
+                    (Some(Action::ExportTrip), _) => self.handle_export(selected, false),
+                    (Some(Action::ExportLegs), _) => self.handle_export(selected, true),
+                    (Some(Action::Copy), _) => self.handle_copy(selected),
+                    (Some(Action::Maximize), _) => self.maximized = !self.maximized,
+                    (Some(Action::ShowLog), _) => self.open_log(),
+                    (Some(Action::Sort), _) => {
+                        self.sort_key = self.sort_key.next();
+                        self.apply_routes_view();
+                        state.view_changed(self.routes.len());
+                    }
+                    (Some(Action::ReverseSort), _) => {
+                        self.sort_descending = !self.sort_descending;
+                        self.apply_routes_view();
+                        state.view_changed(self.routes.len());
+                    }
+                    (Some(Action::Filter), _) => self.start_filter(),
+                    (Some(Action::ShowDisruptions), _) => self.fetch_disruptions(),
+                    (Some(Action::ShowHelp), _) => self.show_help = true,
+                    (Some(Action::Leave), _) => self.input_mode = InputMode::Normal,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Adds a message to the log, timestamped with the current frame time.
+    pub fn log(&mut self, severity: Severity, text: impl Into<String>) {
+        self.messages.push(Message {
+            time: self.now,
+            severity,
+            text: text.into(),
+        });
+    }
+
+    /// Recent messages that are still shown as toasts, oldest first.
+    pub fn toasts(&self) -> Vec<&Message> {
+        let toasts = self
+            .messages
+            .iter()
+            .rev()
+            .take_while(|msg| msg.is_toast(&self.now))
+            .take(MAX_TOASTS)
+            .collect::<Vec<&Message>>();
+        toasts.into_iter().rev().collect()
+    }
+
+    /// Replaces the connections with newly fetched ones, keeping sort and filter.
+    pub fn set_routes(&mut self, routes: Vec<Connection>) {
+        self.plan.clear();
+        if self.details_view == DetailsView::Plan {
+            self.details_view = DetailsView::List;
+        }
+        self.routes = routes;
+        self.filtered_routes.clear();
+        self.apply_routes_view();
+    }
+
+    /// Shows the parts of a trip over `via` in the table, in the order of the
+    /// trip, and the whole plan in the details pane.
+    pub fn set_plan(&mut self, via: Vec<Stop>, parts: Vec<Connection>) {
+        self.routes = parts;
+        self.filtered_routes.clear();
+        self.plan = via;
+        self.details_view = DetailsView::Plan;
+    }
+
+    /// Shows the stations near `location`, the nearest selected.
+    pub fn set_nearby(&mut self, location: String, stations: Vec<NearbyStation>) {
+        self.nearby = stations;
+        self.nearby_location = location;
+        self.nearby_selected = 0;
+        self.nearby_board = false;
+        self.show_nearby = true;
+    }
+
+    /// Looks up the stations near the destination if it is focused, else near
+    /// the start, or near the current position if that is empty.
+    fn fetch_nearby(&mut self) {
+        let location = match self.focus {
+            Focus::Destination => &self.destination,
+            _ => &self.start,
+        };
+        let location = match location.trim() {
+            "" => HERE.to_string(),
+            location => location.to_string(),
         };
+        let gpsd = self.gpsd.clone();
+        self.send_io(IoEvent::GetNearby(location, gpsd));
+    }
+
+    fn handle_nearby_key(&mut self, code: KeyCode) {
+        let selected = self
+            .nearby
+            .get(self.nearby_selected)
+            .map(|s| s.name.clone());
+        match (self.keymap.nearby_action(code), selected) {
+            (Some(Action::NextEntry), _) if self.nearby_selected + 1 < self.nearby.len() => {
+                self.nearby_selected += 1
+            }
+            (Some(Action::PreviousEntry), _) => {
+                self.nearby_selected = self.nearby_selected.saturating_sub(1)
+            }
+            (Some(Action::ShowDepartures), Some(_)) => self.nearby_board = true,
+            (Some(Action::SetStart), Some(name)) => {
+                self.input_start = name.clone();
+                self.start = name;
+                self.focus = Focus::Start;
+                self.show_nearby = false;
+            }
+            (Some(Action::SetDestination), Some(name)) => {
+                self.input_destination = name.clone();
+                self.destination = name;
+                self.focus = Focus::Destination;
+                self.show_nearby = false;
+            }
+            // The board goes back to the list, the list closes
+            (Some(Action::Leave), _) if self.nearby_board => self.nearby_board = false,
+            (Some(Action::Leave), _) => self.show_nearby = false,
+            _ => {}
+        }
+    }
+
+    /// Shows the overview of all disruptions from the top.
+    pub fn set_disruptions(&mut self, disruptions: Vec<Disruption>) {
+        self.disruptions = disruptions;
+        self.disruptions_scroll = 0;
+        self.show_disruptions = true;
+    }
+
+    fn fetch_disruptions(&mut self) {
+        self.send_io(IoEvent::GetDisruptions);
+    }
+
+    /// Filters and sorts all fetched connections into `routes`. The parts of a
+    /// trip plan are neither filtered nor sorted. The selection of the table
+    /// has to follow, see `RoutesTableState::view_changed`.
+    fn apply_routes_view(&mut self) {
+        if !self.plan.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.routes);
+        all.append(&mut self.filtered_routes);
+        let (mut shown, hidden): (Vec<Connection>, Vec<Connection>) = all
+            .into_iter()
+            .partition(|conn| matches_filter(conn, &self.filter));
+        sort_routes(&mut shown, self.sort_key, self.sort_descending);
+        self.routes = shown;
+        self.filtered_routes = hidden;
+    }
+
+    fn start_filter(&mut self) {
+        self.focus = Focus::Routes;
+        self.input_mode = InputMode::Editing;
+        self.editing_filter = true;
+    }
+
+    /// Opens the message log scrolled to the latest message.
+    fn open_log(&mut self) {
+        self.show_log = true;
+        self.log_scroll = u16::MAX;
     }
 
     fn handle_i_key(&mut self) {
         match self.focus {
             Focus::Start => self.input_mode = InputMode::Editing,
+            Focus::Via => self.input_mode = InputMode::Editing,
             Focus::Destination => self.input_mode = InputMode::Editing,
             Focus::Routes => self.input_mode = InputMode::Table,
+            Focus::Details => self.input_mode = InputMode::Table,
+            Focus::Notifications => self.input_mode = InputMode::Table,
             Focus::Date => self.input_mode = InputMode::Editing,
             Focus::Time => self.input_mode = InputMode::Editing,
             Focus::Arrival => self.is_arrival = !self.is_arrival,
-            Focus::Ubahn => self.use_ubahn = !self.use_ubahn,
-            Focus::Sbahn => self.use_sbahn = !self.use_sbahn,
-            Focus::Tram => self.use_tram = !self.use_tram,
-            Focus::Bus => self.use_bus = !self.use_bus,
+            Focus::Mode(i) => self.modes[i] = !self.modes[i],
         }
     }
 
     fn handle_h_key(&mut self) {
         match self.focus {
             Focus::Start => {}
-            Focus::Destination => self.focus = Focus::Start,
+            Focus::Via => self.focus = Focus::Start,
+            Focus::Destination => self.focus = Focus::Via,
             Focus::Date => {}
             Focus::Time => self.focus = Focus::Date,
             Focus::Arrival => self.focus = Focus::Time,
-            Focus::Ubahn => self.focus = Focus::Arrival,
-            Focus::Sbahn => self.focus = Focus::Ubahn,
-            Focus::Tram => self.focus = Focus::Sbahn,
-            Focus::Bus => self.focus = Focus::Tram,
+            Focus::Mode(0) => self.focus = Focus::Arrival,
+            Focus::Mode(i) => self.focus = Focus::Mode(i - 1),
             Focus::Routes => {}
+            Focus::Details => self.focus = Focus::Routes,
+            Focus::Notifications => {}
         }
     }
     fn handle_j_key(&mut self) {
         match self.focus {
             Focus::Start => self.focus = Focus::Date,
-            Focus::Destination => self.focus = Focus::Date,
+            Focus::Via => self.focus = Focus::Mode(0),
+            Focus::Destination => self.focus = Focus::Mode(FIRST_MODE_ABOVE_DETAILS),
             Focus::Date => self.focus = Focus::Routes,
             Focus::Time => self.focus = Focus::Routes,
             Focus::Arrival => self.focus = Focus::Routes,
-            Focus::Ubahn => self.focus = Focus::Routes,
-            Focus::Sbahn => self.focus = Focus::Routes,
-            Focus::Tram => self.focus = Focus::Routes,
-            Focus::Bus => self.focus = Focus::Routes,
-            Focus::Routes => {}
+            Focus::Mode(i) if i < FIRST_MODE_ABOVE_DETAILS => self.focus = Focus::Routes,
+            Focus::Mode(_) => self.focus = Focus::Details,
+            Focus::Routes => self.focus = Focus::Notifications,
+            Focus::Details => {}
+            Focus::Notifications => {}
         }
     }
 
     fn handle_k_key(&mut self) {
         match self.focus {
             Focus::Start => {}
+            Focus::Via => {}
             Focus::Destination => {}
             Focus::Date => self.focus = Focus::Start,
             Focus::Time => self.focus = Focus::Start,
-            Focus::Arrival => self.focus = Focus::Destination,
-            Focus::Ubahn => self.focus = Focus::Destination,
-            Focus::Sbahn => self.focus = Focus::Destination,
-            Focus::Tram => self.focus = Focus::Destination,
-            Focus::Bus => self.focus = Focus::Destination,
+            Focus::Arrival => self.focus = Focus::Start,
+            Focus::Mode(i) if i < FIRST_MODE_ABOVE_DETAILS => self.focus = Focus::Via,
+            Focus::Mode(_) => self.focus = Focus::Destination,
             Focus::Routes => self.focus = Focus::Date,
+            Focus::Details => self.focus = Focus::Mode(FIRST_MODE_ABOVE_DETAILS),
+            Focus::Notifications => self.focus = Focus::Routes,
         }
     }
 
     fn handle_l_key(&mut self) {
         match self.focus {
-            Focus::Start => self.focus = Focus::Destination,
+            Focus::Start => self.focus = Focus::Via,
+            Focus::Via => self.focus = Focus::Destination,
             Focus::Destination => {}
             Focus::Date => self.focus = Focus::Time,
             Focus::Time => self.focus = Focus::Arrival,
-            Focus::Arrival => self.focus = Focus::Ubahn,
-            Focus::Ubahn => self.focus = Focus::Sbahn,
-            Focus::Sbahn => self.focus = Focus::Tram,
-            Focus::Tram => self.focus = Focus::Bus,
-            Focus::Bus => {}
-            Focus::Routes => {}
+            Focus::Arrival => self.focus = Focus::Mode(0),
+            Focus::Mode(i) if i + 1 < MODES.len() => self.focus = Focus::Mode(i + 1),
+            Focus::Mode(_) => {}
+            Focus::Routes => self.focus = Focus::Details,
+            Focus::Details => {}
+            Focus::Notifications => self.focus = Focus::Details,
+        }
+    }
+
+    fn toggle_details_view(&mut self) {
+        self.details_view = match self.details_view {
+            DetailsView::List => DetailsView::Timeline,
+            DetailsView::Timeline => DetailsView::Map,
+            DetailsView::Map if !self.plan.is_empty() => DetailsView::Plan,
+            DetailsView::Map | DetailsView::Plan => DetailsView::List,
+        }
+    }
+
+    fn zoom_map(&mut self, factor: f64) {
+        self.map_zoom = (self.map_zoom * factor).clamp(1.0, 64.0);
+    }
+
+    fn pan_map(&mut self, dx: f64, dy: f64) {
+        // Pan by a fraction of the visible area, so it feels the same at every zoom level
+        self.map_pan.0 += dx / self.map_zoom;
+        self.map_pan.1 += dy / self.map_zoom;
+    }
+
+    fn reset_map(&mut self) {
+        self.map_zoom = 1.0;
+        self.map_pan = (0.0, 0.0);
+    }
+
+    fn handle_export(&mut self, selected: Option<usize>, per_leg: bool) {
+        let conn = match selected.and_then(|idx| self.routes.get(idx)) {
+            Some(conn) => conn,
+            None => return,
+        };
+        let file_name = ics_file_name(conn);
+        match std::fs::write(&file_name, connection_to_ics(conn, per_leg)) {
+            Ok(_) => self.log(
+                Severity::Info,
+                format!("Exported connection to {}", file_name),
+            ),
+            Err(e) => self.log(
+                Severity::Error,
+                format!("Could not write {}: {}", file_name, e),
+            ),
+        }
+    }
+
+    fn handle_copy(&mut self, selected: Option<usize>) {
+        let conn = match selected.and_then(|idx| self.routes.get(idx)) {
+            Some(conn) => conn,
+            None => return,
+        };
+        match copy_to_clipboard(&connection_summary(conn)) {
+            Ok(_) => self.log(Severity::Info, "Copied connection to clipboard"),
+            Err(e) => self.log(
+                Severity::Error,
+                format!("Could not copy to clipboard: {}", e),
+            ),
         }
     }
 
     fn handle_typing(&mut self, character: char) {
         match self.focus {
             Focus::Start => self.input_start.push(character),
+            Focus::Via => self.input_via.push(character),
             Focus::Destination => self.input_destination.push(character),
             Focus::Date => self.input_date.push(character),
             Focus::Time => self.input_time.push(character),
@@ -203,6 +644,9 @@ impl App {
             Focus::Start => {
                 self.input_start.pop();
             }
+            Focus::Via => {
+                self.input_via.pop();
+            }
             Focus::Destination => {
                 self.input_destination.pop();
             }
@@ -220,6 +664,13 @@ impl App {
         self.input_mode = InputMode::Normal;
         match self.focus {
             Focus::Start => self.start = self.input_start.clone(),
+            Focus::Via => match parse_via(&self.input_via) {
+                Ok(via) => {
+                    self.via = via;
+                    self.wrong_via = false;
+                }
+                Err(_) => self.wrong_via = true,
+            },
             Focus::Destination => self.destination = self.input_destination.clone(),
             Focus::Date => {
                 let date = match NaiveDate::parse_from_str(&self.input_date, "%d.%m.%Y") {
@@ -253,16 +704,112 @@ impl App {
 #[derive(Debug, Default)]
 pub struct RoutesTableState {
     pub table_state: TableState,
+    /// Selected line in the details list of the selected connection
+    pub details_state: ListState,
+    /// Legs of the selected connection with hidden intermediate stops
+    pub collapsed_legs: HashSet<usize>,
+    pub notifications_scroll: u16,
 }
 
 impl RoutesTableState {
     pub fn new() -> Self {
         RoutesTableState {
             table_state: TableState::default(),
+            details_state: ListState::default(),
+            collapsed_legs: HashSet::new(),
+            notifications_scroll: 0,
+        }
+    }
+
+    /// Leg index of every line in the details list, see `details_list`.
+    /// A leg has lines for its start and end and one per intermediate stop, or
+    /// a single line for all stops if it is collapsed.
+    pub fn details_legs(&self, app: &App) -> Vec<usize> {
+        let conn = match self.selected_route(app) {
+            Some(conn) => conn,
+            None => return Vec::new(),
+        };
+        let mut legs = Vec::new();
+        for (leg, cp) in conn.parts.iter().enumerate() {
+            let stops = match cp.intermediate_stops.len() {
+                0 => 0,
+                _ if self.collapsed_legs.contains(&leg) => 1,
+                n => n,
+            };
+            legs.extend(std::iter::repeat_n(leg, stops + 2));
+        }
+        legs
+    }
+
+    fn next_details_line(&mut self, app: &App) {
+        let len = self.details_legs(app).len();
+        if len == 0 {
+            return;
+        }
+        let i = match self.details_state.selected() {
+            Some(i) => (i + 1).min(len - 1),
+            None => 0,
+        };
+        self.details_state.select(Some(i));
+    }
+
+    fn previous_details_line(&mut self) {
+        let i = self.details_state.selected().unwrap_or(0).saturating_sub(1);
+        self.details_state.select(Some(i));
+    }
+
+    /// Collapses or expands the leg of the selected details line and keeps the
+    /// selection on the first line of that leg.
+    fn toggle_stops(&mut self, app: &App) {
+        let legs = self.details_legs(app);
+        let leg = match self.details_state.selected().and_then(|i| legs.get(i)) {
+            Some(leg) => *leg,
+            None => return,
+        };
+        if !self.collapsed_legs.remove(&leg) {
+            self.collapsed_legs.insert(leg);
         }
+        let first_line = self.details_legs(app).iter().position(|l| *l == leg);
+        self.details_state.select(first_line);
+    }
+
+    /// Details and notifications belong to the selected connection, so they
+    /// start from the top again when it changes.
+    fn reset_panes(&mut self) {
+        self.details_state = ListState::default();
+        self.collapsed_legs.clear();
+        self.notifications_scroll = 0;
+    }
+
+    /// The selected connection, `None` if the selection is behind the last row.
+    pub fn selected_route<'a>(&self, app: &'a App) -> Option<&'a Connection> {
+        self.table_state
+            .selected()
+            .and_then(|idx| app.routes.get(idx))
+    }
+
+    /// Moves the selection back into a table of `len` rows, e.g. after
+    /// filtering, sorting or a new fetch.
+    pub fn clamp(&mut self, len: usize) {
+        if let Some(selected) = self.table_state.selected() {
+            if selected >= len {
+                self.table_state.select(len.checked_sub(1));
+                self.reset_panes();
+            }
+        }
+    }
+
+    /// After filtering or sorting the selected row shows another connection,
+    /// see `App::apply_routes_view`.
+    fn view_changed(&mut self, len: usize) {
+        self.clamp(len);
+        self.reset_panes();
     }
 
     pub fn next_table_entry(&mut self, app: &App) {
+        if app.routes.is_empty() {
+            return;
+        }
         let i = match &app.input_mode {
             InputMode::Table => match self.table_state.selected() {
                 Some(i) => {
@@ -277,9 +824,13 @@ impl RoutesTableState {
             _ => 0,
         };
         self.table_state.select(Some(i));
+        self.reset_panes();
     }
 
     pub fn previous_table_entry(&mut self, app: &App) {
+        if app.routes.is_empty() {
+            return;
+        }
         let i = match &app.input_mode {
             InputMode::Table => match self.table_state.selected() {
                 Some(i) => {
@@ -294,6 +845,7 @@ impl RoutesTableState {
             _ => 0,
         };
         self.table_state.select(Some(i));
+        self.reset_panes();
     }
 }
 
@@ -309,37 +861,14 @@ pub async fn run_app<B: Backend>(
     });
     loop {
         let mut app = cloned_app.lock().await;
+        app.now = Local::now();
         terminal.draw(|f| ui(f, &mut app, &mut routes_table_state))?;
 
         if crossterm::event::poll(Duration::from_millis(10)).unwrap() {
             if let Event::Key(key) = event::read()? {
-                match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('i') | KeyCode::Enter => app.handle_i_key(),
-                        KeyCode::Char('h') | KeyCode::Left => app.handle_h_key(),
-                        KeyCode::Char('l') | KeyCode::Right => app.handle_l_key(),
-                        KeyCode::Char('j') | KeyCode::Down => app.handle_j_key(),
-                        KeyCode::Char('k') | KeyCode::Up => app.handle_k_key(),
-                        KeyCode::Char('f') | KeyCode::Char(' ') => app.handle_fetch().await,
-                        _ => {}
-                    },
-                    InputMode::Editing => match key.code {
-                        KeyCode::Char(c) => app.handle_typing(c),
-                        KeyCode::Backspace => app.handle_backspace(),
-                        KeyCode::Esc | KeyCode::Enter => app.handle_esc(),
-                        _ => {}
-                    },
-                    InputMode::Table => match key.code {
-                        KeyCode::Char('j') | KeyCode::Down => {
-                            routes_table_state.next_table_entry(&app)
-                        }
-                        KeyCode::Char('k') | KeyCode::Up => {
-                            routes_table_state.previous_table_entry(&app)
-                        }
-                        KeyCode::Esc | KeyCode::Enter => app.input_mode = InputMode::Normal,
-                        _ => {}
-                    },
+                app.handle_key(key, &mut routes_table_state);
+                if app.should_quit {
+                    return Ok(());
                 }
             }
             app.frames += 1;
@@ -0,0 +1,445 @@
+use std::{io, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use clap::{Arg, ArgAction, ArgMatches, Args, FromArgMatches, Parser, Subcommand, ValueEnum};
+use crossterm::{
+    cursor::MoveTo,
+    execute,
+    terminal::{Clear, ClearType},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Config;
+use crate::export::connection_to_ics;
+use crate::gps::HERE;
+use crate::modes::{parse_mode, TransportMode, MODES};
+use crate::networking::{
+    fetch_departures, fetch_nearby, fetch_routes, Departure, DeparturesParams, RoutesParams,
+    NEARBY_STATIONS,
+};
+use crate::routes_view::WalkingSpeed;
+use crate::status::{fetch_status, Status, StatusClass, StatusFormat};
+use crate::ui_elements::{
+    departure_cells, nearby_cells, route_cells, DEPARTURES_HEADER, NEARBY_HEADER, ROUTES_HEADER,
+};
+
+/// Text user interface for services of the Münchner Verkehrsgesellschaft.
+///
+/// Without a subcommand the interactive TUI is started.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Look up connections between two stations and print them
+    Route(RouteArgs),
+    /// Show the next departures at a station
+    Departures(DeparturesArgs),
+    /// Print the next connection of a favorite trip or station for a status bar
+    Status(StatusArgs),
+    /// List the stations closest to the current position or a place
+    Nearby(NearbyArgs),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Args)]
+pub struct RouteArgs {
+    /// Name of the start station, `here` for the position from gpsd
+    pub from: String,
+    /// Name of the destination station, `here` for the position from gpsd
+    pub to: String,
+    /// Time in the format HH:MM, defaults to now
+    #[arg(long)]
+    pub at: Option<String>,
+    /// Date in the format DD.MM.YYYY, defaults to today
+    #[arg(long)]
+    pub date: Option<String>,
+    /// Interpret the time as arrival instead of departure
+    #[arg(long)]
+    pub arrival: bool,
+    /// Exclude a mode of transport, e.g. `--no bus --no tram`
+    #[arg(long = "no", value_name = "MODE", value_parser = parse_mode)]
+    pub no_mode: Vec<TransportMode>,
+    #[command(flatten)]
+    pub no_flags: NoModeFlags,
+    /// Include a mode that is off by default, e.g. `--with ruftaxi`
+    #[arg(long = "with", value_name = "MODE", value_parser = parse_mode)]
+    pub with_mode: Vec<TransportMode>,
+    /// Leave out connections with this line, in addition to the config file,
+    /// e.g. `--avoid-line U6`
+    #[arg(long, value_name = "LINE")]
+    pub avoid_line: Vec<String>,
+    /// Leave out connections changing at or passing this station
+    #[arg(long, value_name = "STATION")]
+    pub avoid_station: Vec<String>,
+    /// Fetch later connections until this many are left after avoiding lines
+    /// and stations
+    #[arg(long, value_name = "N")]
+    pub min_connections: Option<usize>,
+    /// Walking speed for changes, overrides the config file
+    #[arg(long, value_enum)]
+    pub walking_speed: Option<WalkingSpeed>,
+    /// Leave out connections with a reported elevator outage. This only filters
+    /// on the messages, it does not route step-free
+    #[arg(long)]
+    pub step_free: bool,
+    /// Longest walk in meters
+    #[arg(long, value_name = "METERS")]
+    pub max_walk: Option<u32>,
+    /// Minimum time for a change in minutes
+    #[arg(long, value_name = "MINUTES")]
+    pub min_change_time: Option<u32>,
+    /// Print the first connection as iCalendar instead, as one event for the
+    /// whole trip or one per leg
+    #[arg(long, value_enum, value_name = "EVENTS")]
+    pub ics: Option<IcsEvents>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+/// Events of the iCalendar export of `route`, see `connection_to_ics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IcsEvents {
+    Trip,
+    Legs,
+}
+
+/// The `--no-<MODE>` flags of `route`, one for each mode of `MODES`, e.g.
+/// `--no-bus` for `--no bus`.
+#[derive(Debug, Clone, Default)]
+pub struct NoModeFlags {
+    pub modes: Vec<TransportMode>,
+}
+
+fn no_flag(mode: &TransportMode) -> String {
+    format!("no-{}", mode.key)
+}
+
+impl FromArgMatches for NoModeFlags {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let modes = MODES
+            .iter()
+            .filter(|mode| matches.get_flag(&no_flag(mode)))
+            .copied()
+            .collect();
+        Ok(NoModeFlags { modes })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for NoModeFlags {
+    fn augment_args(cmd: clap::Command) -> clap::Command {
+        cmd.args(MODES.iter().map(|mode| {
+            Arg::new(no_flag(mode))
+                .long(no_flag(mode))
+                .action(ArgAction::SetTrue)
+                .help(format!(
+                    "Exclude {}, same as `--no {}`",
+                    mode.name, mode.key
+                ))
+        }))
+    }
+
+    fn augment_args_for_update(cmd: clap::Command) -> clap::Command {
+        Self::augment_args(cmd)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct DeparturesArgs {
+    /// Name of the station
+    pub station: String,
+    /// Only show these lines, e.g. `--line U3 --line U6`
+    #[arg(long)]
+    pub line: Vec<String>,
+    /// Only show departures whose destination contains this text
+    #[arg(long)]
+    pub direction: Option<String>,
+    /// Only show these modes of transport, e.g. `--mode ubahn`
+    #[arg(long, value_parser = parse_mode)]
+    pub mode: Vec<TransportMode>,
+    /// Maximum number of departures to fetch
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+    /// Refresh the board in place every SECONDS seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub watch: Option<u64>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusArgs {
+    /// Name of a favorite from the config file
+    pub favorite: String,
+    /// Format understood by the status bar
+    #[arg(long, value_enum, default_value_t = StatusFormat::Waybar)]
+    pub format: StatusFormat,
+    /// Keep running and print a new line every SECONDS seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub watch: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct NearbyArgs {
+    /// Station, address or coordinates like `48.1374, 11.5755`
+    #[arg(default_value = HERE)]
+    pub location: String,
+    /// Maximum number of stations to list
+    #[arg(long, default_value_t = NEARBY_STATIONS)]
+    pub limit: usize,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+impl RouteArgs {
+    fn routes_params(&self, config: Config) -> Result<RoutesParams> {
+        let now = Local::now();
+        let date = match &self.date {
+            Some(d) => NaiveDate::parse_from_str(d, "%d.%m.%Y")
+                .map_err(|_| anyhow!("Please enter a valid date: {}", d))?,
+            None => now.date_naive(),
+        };
+        let time = match &self.at {
+            Some(t) => NaiveTime::parse_from_str(t, "%H:%M")
+                .map_err(|_| anyhow!("Please enter a valid time: {}", t))?,
+            None => now.time(),
+        };
+        let time = Local
+            .from_local_datetime(&date.and_time(time))
+            .single()
+            .ok_or_else(|| anyhow!("Ambiguous local time: {} {}", date, time))?;
+        let gpsd = config.gpsd_address();
+        let mut avoid = config.avoid;
+        avoid.lines.extend(self.avoid_line.iter().cloned());
+        avoid.stations.extend(self.avoid_station.iter().cloned());
+        if let Some(n) = self.min_connections {
+            avoid.min_connections = n;
+        }
+        let mut preferences = config.preferences;
+        preferences.walking_speed = self.walking_speed.unwrap_or(preferences.walking_speed);
+        preferences.step_free |= self.step_free;
+        preferences.max_walk = self.max_walk.or(preferences.max_walk);
+        preferences.min_change_time = self.min_change_time.or(preferences.min_change_time);
+
+        Ok(RoutesParams {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            time,
+            arrival: self.arrival,
+            transport_types: MODES
+                .iter()
+                .filter(|mode| {
+                    (mode.default || self.with_mode.contains(mode))
+                        && !self.no_mode.contains(mode)
+                        && !self.no_flags.modes.contains(mode)
+                })
+                .map(|mode| mode.api_name.to_string())
+                .collect(),
+            avoid,
+            preferences,
+            gpsd,
+        })
+    }
+}
+
+fn format_table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths = header.iter().map(|h| h.width()).collect::<Vec<usize>>();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.width());
+        }
+    }
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![format_row(header.to_vec())];
+    for row in rows {
+        lines.push(format_row(row.iter().map(|c| c.as_str()).collect()));
+    }
+    lines.join("\n")
+}
+
+fn format_json(header: &[&str], rows: &[Vec<String>]) -> Result<String> {
+    let rows = rows
+        .iter()
+        .map(|row| {
+            header
+                .iter()
+                .map(|h| h.to_lowercase())
+                .zip(row.iter().cloned().map(serde_json::Value::String))
+                .collect::<serde_json::Map<String, serde_json::Value>>()
+        })
+        .collect::<Vec<_>>();
+    Ok(serde_json::to_string_pretty(&rows)?)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn format_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut lines = vec![header.join(",")];
+    for row in rows {
+        lines.push(
+            row.iter()
+                .map(|c| csv_field(c))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+    }
+    lines.join("\n")
+}
+
+fn format_output(format: OutputFormat, header: &[&str], rows: &[Vec<String>]) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(format_table(header, rows)),
+        OutputFormat::Json => format_json(header, rows),
+        OutputFormat::Csv => Ok(format_csv(header, rows)),
+    }
+}
+
+pub async fn run_route(args: RouteArgs) -> Result<()> {
+    let config = Config::load()?;
+    let routes = fetch_routes(&args.routes_params(config)?).await?;
+    if let Some(events) = args.ics {
+        let conn = routes
+            .first()
+            .ok_or_else(|| anyhow!("No connection from {} to {}", args.from, args.to))?;
+        print!("{}", connection_to_ics(conn, events == IcsEvents::Legs));
+        return Ok(());
+    }
+    let now = Local::now();
+    let rows = routes
+        .iter()
+        .map(|conn| route_cells(conn, &now, None))
+        .collect::<Vec<Vec<String>>>();
+    println!("{}", format_output(args.format, &ROUTES_HEADER, &rows)?);
+    Ok(())
+}
+
+fn departure_rows(departures: &[Departure], args: &DeparturesArgs) -> Vec<Vec<String>> {
+    let now = Local::now();
+    departures
+        .iter()
+        .filter(|dep| {
+            args.line.is_empty() || args.line.iter().any(|l| l.eq_ignore_ascii_case(&dep.label))
+        })
+        .filter(|dep| match &args.direction {
+            Some(direction) => dep
+                .destination
+                .to_lowercase()
+                .contains(&direction.to_lowercase()),
+            None => true,
+        })
+        .map(|dep| departure_cells(dep, &now, None))
+        .collect()
+}
+
+pub async fn run_departures(args: DeparturesArgs) -> Result<()> {
+    let params = DeparturesParams {
+        station: args.station.clone(),
+        limit: args.limit,
+        transport_types: args.mode.iter().map(|m| m.api_name.to_string()).collect(),
+    };
+    loop {
+        let output = match fetch_departures(&params).await {
+            Ok(departures) => format_output(
+                args.format,
+                &DEPARTURES_HEADER,
+                &departure_rows(&departures, &args),
+            )?,
+            // A board left open in a split keeps refreshing after a network error
+            Err(e) if args.watch.is_some() => format!("Fetching failed: {:#}", e),
+            Err(e) => return Err(e),
+        };
+        match args.watch {
+            Some(seconds) => {
+                let mut stdout = io::stdout();
+                execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+                println!("{} ({})", args.station, Local::now().format("%H:%M:%S"));
+                println!("{}", output);
+                tokio::time::sleep(Duration::from_secs(seconds)).await;
+            }
+            None => {
+                println!("{}", output);
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub async fn run_status(args: StatusArgs) -> Result<()> {
+    let config = Config::load()?;
+    let favorite = config
+        .favorites
+        .get(&args.favorite)
+        .ok_or_else(|| anyhow!("No favorite named {} in the config file", args.favorite))?;
+    loop {
+        let line = match fetch_status(favorite, &config).await {
+            Ok(status) => status.render(args.format),
+            // A status bar has no place for an error message, so show it as a disruption
+            Err(e) => Status {
+                text: "mvg: error".to_string(),
+                tooltip: e.to_string(),
+                class: StatusClass::Disrupted,
+            }
+            .render(args.format),
+        };
+        println!("{}", line);
+        match args.watch {
+            Some(seconds) => tokio::time::sleep(Duration::from_secs(seconds)).await,
+            None => return Ok(()),
+        }
+    }
+}
+
+pub async fn run_nearby(args: NearbyArgs) -> Result<()> {
+    let config = Config::load()?;
+    let stations = fetch_nearby(&args.location, &config.gpsd_address(), args.limit).await?;
+    let rows = stations
+        .iter()
+        .map(|station| nearby_cells(station, config.preferences.walking_speed))
+        .collect::<Vec<Vec<String>>>();
+    println!("{}", format_output(args.format, &NEARBY_HEADER, &rows)?);
+    Ok(())
+}
+
+pub async fn run_command(command: Command) -> Result<()> {
+    match command {
+        Command::Route(args) => run_route(args).await,
+        Command::Departures(args) => run_departures(args).await,
+        Command::Status(args) => run_status(args).await,
+        Command::Nearby(args) => run_nearby(args).await,
+    }
+}
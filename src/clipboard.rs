@@ -0,0 +1,21 @@
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Builds the OSC 52 escape sequence that asks the terminal to put `text` into
+/// the system clipboard. Inside tmux the sequence is wrapped in a passthrough,
+/// so it reaches the outer terminal.
+fn osc52_sequence(text: &str) -> String {
+    let osc = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;{}\x1b\\", osc.replace('\x1b', "\x1b\x1b"))
+    } else {
+        osc
+    }
+}
+
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52_sequence(text).as_bytes())?;
+    stdout.flush()
+}
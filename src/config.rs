@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::gps::DEFAULT_GPSD_ADDRESS;
+use crate::keymap::Action;
+use crate::routes_view::{Avoid, Preferences};
+use crate::theme::ThemeConfig;
+use crate::ui::LayoutMode;
+
+/// A saved trip or station, referenced by name from the command line.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Favorite {
+    Trip { from: String, to: String },
+    Station { station: String },
+}
+
+/// Contents of `~/.config/mvg-tui/config.toml`.
+///
+/// ```toml
+/// layout = "narrow"
+/// gpsd = "127.0.0.1:2947"
+///
+/// [avoid]
+/// lines = ["U6"]
+///
+/// [preferences]
+/// step_free = true  # elevator outage filter only
+///
+/// [favorites.work]
+/// from = "Marienplatz"
+/// to = "Garching"
+///
+/// [favorites.home]
+/// station = "Giesing"
+///
+/// [keys.normal]
+/// focus_left = ["d", "Left"]
+///
+/// [theme]
+/// preset = "colorblind"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub favorites: BTreeMap<String, Favorite>,
+    /// Key names per action, per input mode, see `Keymap::from_config`
+    pub keys: BTreeMap<String, BTreeMap<Action, Vec<String>>>,
+    /// Colour preset and overrides, see `Theme::from_config`
+    pub theme: ThemeConfig,
+    /// Fixed layout instead of choosing it from the terminal size
+    pub layout: LayoutMode,
+    /// Lines and stations to leave out of the connections
+    pub avoid: Avoid,
+    /// Walking speed, the elevator outage filter and limits for walks and changes
+    pub preferences: Preferences,
+    /// Address of gpsd for the `here` pseudo-station, see `gps`
+    pub gpsd: Option<String>,
+}
+
+impl Config {
+    pub fn gpsd_address(&self) -> String {
+        self.gpsd
+            .clone()
+            .unwrap_or_else(|| DEFAULT_GPSD_ADDRESS.to_string())
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mvg-tui").join("config.toml"))
+    }
+
+    /// Loads the config file, falling back to the defaults if it does not exist.
+    pub fn load() -> Result<Config> {
+        let path = match Config::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        };
+        let content =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("parsing {}", path.display()))
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, FixedOffset, Local};
+use mvg_api::routes::Connection;
+
+use crate::app::shown_time;
+use crate::itinerary::{arrival, departure};
+use crate::networking::Disruption;
+use crate::routes_view::is_walk;
+
+/// Group of the disruptions that name no line.
+pub const GENERAL: &str = "General";
+
+/// Disruptions grouped by line label, the lines sorted and the general
+/// messages last. A disruption of several lines is listed for each of them.
+pub fn by_line(disruptions: &[Disruption]) -> Vec<(&str, Vec<&Disruption>)> {
+    let mut groups: Vec<(&str, Vec<&Disruption>)> = Vec::new();
+    for disruption in disruptions {
+        let mut labels = disruption
+            .lines
+            .iter()
+            .map(|line| line.label.as_str())
+            .collect::<Vec<&str>>();
+        labels.sort_unstable();
+        labels.dedup();
+        if labels.is_empty() {
+            labels.push(GENERAL);
+        }
+        for label in labels {
+            match groups.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, group)) => group.push(disruption),
+                None => groups.push((label, vec![disruption])),
+            }
+        }
+    }
+    groups.sort_by_key(|(label, _)| (*label == GENERAL, label.to_string()));
+    groups
+}
+
+/// Whether the validity of a disruption, as shown by `validity`, overlaps the
+/// time from `from` to `to`. Open ends are valid for ever.
+fn valid_during(disruption: &Disruption, from: DateTime<Local>, to: DateTime<Local>) -> bool {
+    disruption.valid_from.is_none_or(|start| start <= to)
+        && disruption.valid_to.is_none_or(|end| end >= from)
+}
+
+/// Labels of the lines of a connection with a disruption during the trip, in
+/// the order they are ridden.
+pub fn affected_lines<'a>(conn: &'a Connection, disruptions: &[Disruption]) -> Vec<&'a str> {
+    let (from, to) = (departure(conn), arrival(conn));
+    let mut affected = conn
+        .parts
+        .iter()
+        .filter(|cp| !is_walk(cp))
+        .map(|cp| cp.line.label.as_str())
+        .filter(|label| {
+            disruptions
+                .iter()
+                .filter(|d| valid_during(d, from, to))
+                .flat_map(|d| &d.lines)
+                .any(|line| line.label.eq_ignore_ascii_case(label))
+        })
+        .collect::<Vec<&str>>();
+    affected.dedup();
+    affected
+}
+
+/// Validity period like `12.04. 08:00 - 14.04. 23:59` in `time_zone` (see
+/// `shown_time`), open ends are left out.
+pub fn validity(disruption: &Disruption, time_zone: Option<FixedOffset>) -> Option<String> {
+    let format = |time: &DateTime<Local>| {
+        shown_time(time, time_zone)
+            .format("%d.%m. %H:%M")
+            .to_string()
+    };
+    match (&disruption.valid_from, &disruption.valid_to) {
+        (Some(from), Some(to)) => Some(format!("{} - {}", format(from), format(to))),
+        (Some(from), None) => Some(format!("from {}", format(from))),
+        (None, Some(to)) => Some(format!("until {}", format(to))),
+        (None, None) => None,
+    }
+}
+
+/// Tags after which the text continues on a new line.
+const BREAKING_TAGS: [&str; 5] = ["p", "br", "li", "div", "ul"];
+
+/// Entities of the descriptions, `&amp;` last so it does not create new ones.
+const ENTITIES: [(&str, &str); 13] = [
+    ("&nbsp;", " "),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&quot;", "\""),
+    ("&#39;", "'"),
+    ("&auml;", "ä"),
+    ("&ouml;", "ö"),
+    ("&uuml;", "ü"),
+    ("&Auml;", "Ä"),
+    ("&Ouml;", "Ö"),
+    ("&Uuml;", "Ü"),
+    ("&szlig;", "ß"),
+    ("&amp;", "&"),
+];
+
+/// The text of an HTML description, one line per paragraph, line break or
+/// list item.
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            // An unclosed tag at the end
+            None => {
+                rest = "";
+                break;
+            }
+        };
+        let name = rest[start + 1..end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if BREAKING_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+        rest = &rest[end + 1..];
+    }
+    text.push_str(rest);
+    let text = ENTITIES
+        .iter()
+        .fold(text, |text, (entity, c)| text.replace(entity, c));
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
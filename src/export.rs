@@ -0,0 +1,177 @@
+use chrono::{DateTime, Local, Utc};
+use mvg_api::routes::{Connection, ConnectionPart, Station};
+
+fn ics_time(time: &DateTime<Local>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds content lines longer than 75 octets as required by RFC 5545.
+fn ics_fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
+
+fn platform(station: &Station) -> String {
+    match &station.platform {
+        Some(p) => format!(" (Pl. {})", p),
+        None => "".to_string(),
+    }
+}
+
+fn line_name(cp: &ConnectionPart) -> &str {
+    if cp.line.label == "FOOTWAY" {
+        "walk"
+    } else {
+        &cp.line.label
+    }
+}
+
+fn describe_part(cp: &ConnectionPart) -> String {
+    let mut desc = format!(
+        "{}: {} {}{} -> {} {}{}",
+        line_name(cp),
+        cp.from.planned_departure.format("%H:%M"),
+        cp.from.name,
+        platform(&cp.from),
+        cp.to.planned_departure.format("%H:%M"),
+        cp.to.name,
+        platform(&cp.to),
+    );
+    for msg in &cp.messages {
+        desc.push_str(&format!("\n  {}", msg));
+    }
+    desc
+}
+
+fn vevent(
+    uid: &str,
+    summary: &str,
+    location: &str,
+    description: &str,
+    start: &DateTime<Local>,
+    end: &DateTime<Local>,
+) -> Vec<String> {
+    vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", ics_time(&Local::now())),
+        format!("DTSTART:{}", ics_time(start)),
+        format!("DTEND:{}", ics_time(end)),
+        format!("SUMMARY:{}", ics_escape(summary)),
+        format!("LOCATION:{}", ics_escape(location)),
+        format!("DESCRIPTION:{}", ics_escape(description)),
+        "END:VEVENT".to_string(),
+    ]
+}
+
+/// Renders a connection as an iCalendar document, either as a single event for
+/// the whole trip or as one event per leg.
+pub fn connection_to_ics(conn: &Connection, per_leg: bool) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//mvg-tui//EN".to_string(),
+    ];
+    let origin = &conn.parts[0].from;
+    let destination = &conn.parts[conn.parts.len() - 1].to;
+    let uid_base = format!("{}-{}", ics_time(&origin.planned_departure), origin.name);
+
+    if per_leg {
+        for (i, cp) in conn.parts.iter().enumerate() {
+            lines.extend(vevent(
+                &format!("{}-{}@mvg-tui", uid_base, i),
+                &format!("{} {} → {}", line_name(cp), cp.from.name, cp.to.name),
+                &cp.from.name,
+                &describe_part(cp),
+                &cp.from.planned_departure,
+                &cp.to.planned_departure,
+            ));
+        }
+    } else {
+        let description = conn
+            .parts
+            .iter()
+            .map(describe_part)
+            .collect::<Vec<String>>()
+            .join("\n");
+        lines.extend(vevent(
+            &format!("{}@mvg-tui", uid_base),
+            &format!("{} → {}", origin.name, destination.name),
+            &origin.name,
+            &description,
+            &origin.planned_departure,
+            &destination.planned_departure,
+        ));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = lines
+        .iter()
+        .map(|l| ics_fold(l))
+        .collect::<Vec<String>>()
+        .join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+/// Formats a connection as a plain-text itinerary that reads well when pasted
+/// into a chat, e.g. `08:14 S8 Marienplatz (Pl. 1) -> 08:52 Garching`.
+pub fn connection_summary(conn: &Connection) -> String {
+    let origin = &conn.parts[0].from;
+    let destination = &conn.parts[conn.parts.len() - 1].to;
+    let changes = conn
+        .parts
+        .iter()
+        .filter(|cp| cp.line.label != "FOOTWAY")
+        .count()
+        .saturating_sub(1);
+    let mut summary = format!(
+        "{} {} -> {} {} ({} min, {} {})",
+        origin.planned_departure.format("%H:%M"),
+        origin.name,
+        destination.planned_departure.format("%H:%M"),
+        destination.name,
+        (destination.planned_departure - origin.planned_departure).num_minutes(),
+        changes,
+        if changes == 1 { "change" } else { "changes" },
+    );
+    for cp in &conn.parts {
+        summary.push_str(&format!(
+            "\n{} {} {}{} -> {} {}{}",
+            cp.from.planned_departure.format("%H:%M"),
+            line_name(cp),
+            cp.from.name,
+            platform(&cp.from),
+            cp.to.planned_departure.format("%H:%M"),
+            cp.to.name,
+            platform(&cp.to),
+        ));
+    }
+    summary
+}
+
+/// File name used when exporting a connection, e.g. `mvg_20230412_0814.ics`.
+pub fn ics_file_name(conn: &Connection) -> String {
+    format!(
+        "mvg_{}.ics",
+        conn.parts[0].from.planned_departure.format("%Y%m%d_%H%M")
+    )
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Where gpsd listens unless configured otherwise with `gpsd = "host:port"`.
+pub const DEFAULT_GPSD_ADDRESS: &str = "127.0.0.1:2947";
+
+/// Name of the pseudo-station at the current position.
+pub const HERE: &str = "here";
+
+/// How long to wait for a position fix.
+const FIX_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One line of gpsd's JSON protocol, only position reports are of interest.
+#[derive(Debug, Deserialize)]
+struct Report {
+    class: String,
+    /// 0 and 1 mean no fix, 2 is 2D and 3 is 3D
+    #[serde(default)]
+    mode: u8,
+    lat: Option<f64>,
+    lon: Option<f64>,
+}
+
+pub fn is_here(name: &str) -> bool {
+    name.trim().eq_ignore_ascii_case(HERE)
+}
+
+/// Latitude and longitude of a TPV report with a fix, `None` for all other lines.
+pub fn parse_report(line: &str) -> Option<(f64, f64)> {
+    let report = serde_json::from_str::<Report>(line).ok()?;
+    match (report.class.as_str(), report.mode, report.lat, report.lon) {
+        ("TPV", 2.., Some(lat), Some(lon)) => Some((lat, lon)),
+        _ => None,
+    }
+}
+
+/// Asks gpsd at `address` for reports and returns the first position fix.
+pub async fn current_position(address: &str) -> Result<(f64, f64)> {
+    let read_fix = async {
+        let mut stream = TcpStream::connect(address)
+            .await
+            .with_context(|| format!("connecting to gpsd at {}", address))?;
+        stream
+            .write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")
+            .await?;
+        let mut lines = BufReader::new(stream).lines();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(position) = parse_report(&line) {
+                return Ok(position);
+            }
+        }
+        Err(anyhow!("gpsd closed the connection without a position"))
+    };
+    tokio::time::timeout(FIX_TIMEOUT, read_fix)
+        .await
+        .map_err(|_| anyhow!("No GPS fix from gpsd at {}", address))?
+}
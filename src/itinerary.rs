@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use mvg_api::routes::Connection;
+
+use crate::networking::{fetch_routes, RoutesParams};
+
+/// A station on the way with the minutes spent there before going on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stop {
+    pub station: String,
+    pub stay_minutes: u32,
+}
+
+/// Parses the via field, stops separated by `;` with an optional stay in
+/// minutes, e.g. `Odeonsplatz +45; Giesing`.
+pub fn parse_via(text: &str) -> Result<Vec<Stop>> {
+    let mut stops = Vec::new();
+    for part in text.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+        let (station, stay_minutes) = match part.rsplit_once('+') {
+            Some((station, stay)) => {
+                let stay = stay
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid stay at {}: {}", station.trim(), stay))?;
+                (station.trim(), stay)
+            }
+            None => (part, 0),
+        };
+        if station.is_empty() {
+            return Err(anyhow!("Missing station in {}", part));
+        }
+        stops.push(Stop {
+            station: station.to_string(),
+            stay_minutes,
+        });
+    }
+    Ok(stops)
+}
+
+pub fn departure(conn: &Connection) -> DateTime<Local> {
+    conn.parts[0].from.planned_departure
+}
+
+pub fn arrival(conn: &Connection) -> DateTime<Local> {
+    conn.parts[conn.parts.len() - 1].to.planned_departure
+}
+
+fn minutes(minutes: u32) -> chrono::Duration {
+    chrono::Duration::from_std(std::time::Duration::from_secs(minutes as u64 * 60)).unwrap()
+}
+
+/// Plans a trip from `rp.from` over the `via` stops to `rp.to` with one query
+/// per segment. With a departure time each segment takes the first connection
+/// after the previous arrival plus the stay, with an arrival time the segments
+/// are planned backwards from the destination. The connections are returned in
+/// the order of the trip.
+pub async fn plan_trip(rp: &RoutesParams, via: &[Stop]) -> Result<Vec<Connection>> {
+    let mut stations = vec![rp.from.as_str()];
+    stations.extend(via.iter().map(|stop| stop.station.as_str()));
+    stations.push(rp.to.as_str());
+    let mut segments = stations
+        .windows(2)
+        .zip(via.iter().map(|stop| stop.stay_minutes).chain([0]))
+        .collect::<Vec<_>>();
+    if rp.arrival {
+        segments.reverse();
+    }
+
+    let mut plan = Vec::new();
+    let mut time = rp.time;
+    for (i, (stations, stay)) in segments.iter().enumerate() {
+        let (from, to) = (stations[0], stations[1]);
+        if rp.arrival && i > 0 {
+            time -= minutes(*stay);
+        }
+        let routes = fetch_routes(&RoutesParams {
+            from: from.to_string(),
+            to: to.to_string(),
+            time,
+            ..rp.clone()
+        })
+        .await?;
+        let conn = match rp.arrival {
+            true => routes
+                .into_iter()
+                .filter(|conn| arrival(conn) <= time)
+                .max_by_key(arrival),
+            false => routes
+                .into_iter()
+                .filter(|conn| departure(conn) >= time)
+                .min_by_key(departure),
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "No connection from {} to {} around {}",
+                from,
+                to,
+                time.format("%H:%M")
+            )
+        })?;
+        time = match rp.arrival {
+            true => departure(&conn),
+            false => arrival(&conn) + minutes(*stay),
+        };
+        plan.push(conn);
+    }
+    if rp.arrival {
+        plan.reverse();
+    }
+    Ok(plan)
+}
@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+
+use crate::app::InputMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    /// Start editing the focused input, toggle the focused option or select the table
+    Activate,
+    FocusLeft,
+    FocusDown,
+    FocusUp,
+    FocusRight,
+    Fetch,
+    ToggleArrival,
+    ToggleDetailsView,
+    /// Go back to normal mode from editing or the table
+    Leave,
+    DeleteChar,
+    NextEntry,
+    PreviousEntry,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanDown,
+    PanUp,
+    PanRight,
+    ResetMap,
+    ExportTrip,
+    ExportLegs,
+    Copy,
+    ShowHelp,
+    ScrollDown,
+    ScrollUp,
+    ToggleStops,
+    /// Show only the focused pane, or go back to all panes
+    Maximize,
+    ShowLog,
+    /// Sort the routes table by the next key
+    Sort,
+    ReverseSort,
+    /// Filter the routes table by line or station
+    Filter,
+    ShowNearby,
+    /// Use the selected nearby station as start
+    SetStart,
+    SetDestination,
+    ShowDepartures,
+    ShowDisruptions,
+}
+
+impl Action {
+    /// Short explanation shown in the help overlay.
+    pub fn description(&self) -> &str {
+        match self {
+            Action::Quit => "Exit",
+            Action::Activate => {
+                "Edit the focused field, toggle the focused option or select the table"
+            }
+            Action::FocusLeft => "Focus the pane to the left",
+            Action::FocusDown => "Focus the pane below",
+            Action::FocusUp => "Focus the pane above",
+            Action::FocusRight => "Focus the pane to the right",
+            Action::Fetch => "Fetch connections",
+            Action::ToggleArrival => "Switch between departure and arrival time",
+            Action::ToggleDetailsView => {
+                "Switch the details pane between list, timeline, map and trip plan"
+            }
+            Action::Leave => "Back to navigation",
+            Action::DeleteChar => "Delete the last character",
+            Action::NextEntry => "Select the next connection, or scroll details and notifications",
+            Action::PreviousEntry => {
+                "Select the previous connection, or scroll details and notifications"
+            }
+            Action::ZoomIn => "Zoom into the map",
+            Action::ZoomOut => "Zoom out of the map",
+            Action::PanLeft => "Move the map to the left",
+            Action::PanDown => "Move the map down",
+            Action::PanUp => "Move the map up",
+            Action::PanRight => "Move the map to the right",
+            Action::ResetMap => "Reset zoom and position of the map",
+            Action::ExportTrip => "Export the connection to an .ics file, one event for the trip",
+            Action::ExportLegs => "Export the connection to an .ics file, one event per leg",
+            Action::Copy => "Copy a summary of the connection to the clipboard",
+            Action::ShowHelp => "Show this help",
+            Action::ScrollDown => "Scroll down",
+            Action::ScrollUp => "Scroll up",
+            Action::ToggleStops => {
+                "Collapse or expand the intermediate stops of a leg in the details"
+            }
+            Action::Maximize => "Show only the focused pane, or all panes again",
+            Action::ShowLog => "Show all messages of this session",
+            Action::Sort => "Sort by departure, arrival, duration, changes, walking time or delay",
+            Action::ReverseSort => "Reverse the sort order",
+            Action::Filter => "Filter by line or station, !U3 hides connections with the U3",
+            Action::ShowNearby => {
+                "List the stations near the start, or the destination if it is focused"
+            }
+            Action::SetStart => "Use the selected station as start",
+            Action::SetDestination => "Use the selected station as destination",
+            Action::ShowDepartures => "Show the departures at the selected station",
+            Action::ShowDisruptions => "Show all current disruptions, grouped by line",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub action: Action,
+    pub keys: Vec<KeyCode>,
+}
+
+/// Keys bound to each action, separately for every input mode.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    normal: Vec<Binding>,
+    editing: Vec<Binding>,
+    table: Vec<Binding>,
+    /// Bindings while the help overlay, the message log or the disruptions are open
+    help: Vec<Binding>,
+    /// Bindings while the nearby stations are shown
+    nearby: Vec<Binding>,
+}
+
+fn bindings(defaults: &[(Action, &[KeyCode])]) -> Vec<Binding> {
+    defaults
+        .iter()
+        .map(|(action, keys)| Binding {
+            action: *action,
+            keys: keys.to_vec(),
+        })
+        .collect()
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use KeyCode::*;
+        Keymap {
+            normal: bindings(&[
+                (Action::Quit, &[Char('q')]),
+                (Action::Activate, &[Char('i'), Enter]),
+                (Action::FocusLeft, &[Char('h'), Left]),
+                (Action::FocusDown, &[Char('j'), Down]),
+                (Action::FocusUp, &[Char('k'), Up]),
+                (Action::FocusRight, &[Char('l'), Right]),
+                (Action::ShowHelp, &[Char('?')]),
+                (Action::Fetch, &[Char('f'), Char(' ')]),
+                (Action::ToggleArrival, &[Char('a')]),
+                (Action::ToggleDetailsView, &[Char('t')]),
+                (Action::Maximize, &[Char('z')]),
+                (Action::ShowLog, &[Char('m')]),
+                (Action::Filter, &[Char('/')]),
+                (Action::ShowNearby, &[Char('n')]),
+                (Action::ShowDisruptions, &[Char('d')]),
+            ]),
+            editing: bindings(&[
+                (Action::Leave, &[Esc, Enter]),
+                (Action::DeleteChar, &[Backspace]),
+            ]),
+            table: bindings(&[
+                (Action::NextEntry, &[Char('j'), Down]),
+                (Action::PreviousEntry, &[Char('k'), Up]),
+                (Action::ToggleDetailsView, &[Char('t')]),
+                (Action::ZoomIn, &[Char('+'), Char('=')]),
+                (Action::ZoomOut, &[Char('-')]),
+                (Action::PanLeft, &[Char('H')]),
+                (Action::PanDown, &[Char('J')]),
+                (Action::PanUp, &[Char('K')]),
+                (Action::PanRight, &[Char('L')]),
+                (Action::ResetMap, &[Char('0')]),
+                (Action::ExportTrip, &[Char('e')]),
+                (Action::ExportLegs, &[Char('E')]),
+                (Action::Copy, &[Char('y')]),
+                (Action::ToggleStops, &[Char('c'), Char(' ')]),
+                (Action::Maximize, &[Char('z')]),
+                (Action::ShowLog, &[Char('m')]),
+                (Action::Sort, &[Char('s')]),
+                (Action::ReverseSort, &[Char('S')]),
+                (Action::Filter, &[Char('/')]),
+                (Action::ShowDisruptions, &[Char('d')]),
+                (Action::ShowHelp, &[Char('?')]),
+                (Action::Leave, &[Esc, Enter]),
+            ]),
+            help: bindings(&[
+                (Action::ScrollDown, &[Char('j'), Down]),
+                (Action::ScrollUp, &[Char('k'), Up]),
+                (Action::Leave, &[Esc, Char('q'), Char('?')]),
+            ]),
+            nearby: bindings(&[
+                (Action::NextEntry, &[Char('j'), Down]),
+                (Action::PreviousEntry, &[Char('k'), Up]),
+                (Action::ShowDepartures, &[Enter]),
+                (Action::SetStart, &[Char('s')]),
+                (Action::SetDestination, &[Char('d')]),
+                (Action::Leave, &[Esc, Char('q'), Char('n')]),
+            ]),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn bindings(&self, mode: InputMode) -> &[Binding] {
+        match mode {
+            InputMode::Normal => &self.normal,
+            InputMode::Editing => &self.editing,
+            InputMode::Table => &self.table,
+        }
+    }
+
+    pub fn help_bindings(&self) -> &[Binding] {
+        &self.help
+    }
+
+    fn find_action(bindings: &[Binding], key: KeyCode) -> Option<Action> {
+        bindings
+            .iter()
+            .find(|b| b.keys.contains(&key))
+            .map(|b| b.action)
+    }
+
+    pub fn action(&self, mode: InputMode, key: KeyCode) -> Option<Action> {
+        Keymap::find_action(self.bindings(mode), key)
+    }
+
+    pub fn help_action(&self, key: KeyCode) -> Option<Action> {
+        Keymap::find_action(&self.help, key)
+    }
+
+    pub fn nearby_bindings(&self) -> &[Binding] {
+        &self.nearby
+    }
+
+    pub fn nearby_action(&self, key: KeyCode) -> Option<Action> {
+        Keymap::find_action(&self.nearby, key)
+    }
+
+    pub fn keys(&self, mode: InputMode, action: Action) -> &[KeyCode] {
+        match self.bindings(mode).iter().find(|b| b.action == action) {
+            Some(binding) => &binding.keys,
+            None => &[],
+        }
+    }
+
+    /// Builds the keymap from the `[keys.<mode>]` tables of the config file.
+    /// Actions that are not configured keep their default keys, except the
+    /// ones taken by a configured action of the same mode. Two configured
+    /// actions of a mode can not share a key.
+    ///
+    /// ```toml
+    /// [keys.normal]
+    /// focus_left = ["d", "Left"]
+    /// fetch = ["Space"]
+    /// ```
+    pub fn from_config(config: &BTreeMap<String, BTreeMap<Action, Vec<String>>>) -> Result<Keymap> {
+        let mut keymap = Keymap::default();
+        for (mode_name, actions) in config {
+            let bindings = match mode_name.as_str() {
+                "normal" => &mut keymap.normal,
+                "editing" => &mut keymap.editing,
+                "table" => &mut keymap.table,
+                "help" => &mut keymap.help,
+                "nearby" => &mut keymap.nearby,
+                _ => return Err(anyhow!("Unknown input mode in [keys.{}]", mode_name)),
+            };
+            let mut configured: Vec<(Action, KeyCode)> = Vec::new();
+            for (action, names) in actions {
+                let keys = names
+                    .iter()
+                    .map(|name| parse_key(name))
+                    .collect::<Result<Vec<KeyCode>>>()?;
+                for key in &keys {
+                    if let Some((other, _)) = configured
+                        .iter()
+                        .find(|(other, k)| k == key && other != action)
+                    {
+                        return Err(anyhow!(
+                            "{} is bound to both {:?} and {:?} in [keys.{}]",
+                            key_name(key),
+                            other,
+                            action,
+                            mode_name
+                        ));
+                    }
+                    configured.push((*action, *key));
+                }
+                for binding in bindings.iter_mut().filter(|b| b.action != *action) {
+                    binding.keys.retain(|k| !keys.contains(k));
+                }
+                match bindings.iter_mut().find(|b| b.action == *action) {
+                    Some(binding) => binding.keys = keys,
+                    None => bindings.push(Binding {
+                        action: *action,
+                        keys,
+                    }),
+                }
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    match name {
+        "Enter" => Ok(KeyCode::Enter),
+        "Esc" => Ok(KeyCode::Esc),
+        "Space" => Ok(KeyCode::Char(' ')),
+        "Backspace" => Ok(KeyCode::Backspace),
+        "Tab" => Ok(KeyCode::Tab),
+        "Left" => Ok(KeyCode::Left),
+        "Right" => Ok(KeyCode::Right),
+        "Up" => Ok(KeyCode::Up),
+        "Down" => Ok(KeyCode::Down),
+        _ => Err(anyhow!("Unknown key: {}", name)),
+    }
+}
+
+/// Name of a key as shown in the help bar.
+pub fn key_name(key: &KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        other => format!("{:?}", other),
+    }
+}
@@ -1,4 +1,17 @@
 pub mod app;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod disruptions;
+pub mod export;
+pub mod gps;
+pub mod itinerary;
+pub mod keymap;
+pub mod messages;
+pub mod modes;
 pub mod networking;
+pub mod routes_view;
+pub mod status;
+pub mod theme;
 pub mod ui;
 pub mod ui_elements;
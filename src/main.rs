@@ -1,16 +1,25 @@
 use std::{io, sync::Arc};
 
 use anyhow::Result;
+use clap::Parser;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use mvg_tui::app::{run_app, App, RoutesTableState};
+use mvg_tui::cli::{run_command, Cli};
+use mvg_tui::config::Config;
+use mvg_tui::keymap::Keymap;
+use mvg_tui::theme::Theme;
 use tokio::sync::Mutex;
 use tui::{backend::CrosstermBackend, Terminal};
 
 async fn run_tui() -> Result<()> {
+    let config = Config::load()?;
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme = Theme::from_config(&config.theme)?;
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -18,7 +27,14 @@ async fn run_tui() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     let (tx, rx) = tokio::sync::mpsc::channel(100);
-    let app = Arc::new(Mutex::new(App::new(tx)));
+    let mut app = App::new(tx);
+    app.keymap = keymap;
+    app.theme = theme;
+    app.layout = config.layout;
+    app.gpsd = config.gpsd_address();
+    app.avoid = config.avoid;
+    app.preferences = config.preferences;
+    let app = Arc::new(Mutex::new(app));
     let routes_table_state = RoutesTableState::new();
     let res = run_app(&mut terminal, app, routes_table_state, rx).await;
 
@@ -39,6 +55,10 @@ async fn run_tui() -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    run_tui().await?;
+    let cli = Cli::parse();
+    match cli.command {
+        Some(command) => run_command(command).await?,
+        None => run_tui().await?,
+    }
     Ok(())
 }
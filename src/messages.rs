@@ -0,0 +1,37 @@
+use chrono::{DateTime, Local};
+
+/// How long a message is shown as toast after it was logged.
+pub const TOAST_SECONDS: i64 = 5;
+/// Maximum number of toasts shown at the same time, older ones are only in the log.
+pub const MAX_TOASTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(&self) -> &str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARN",
+            Severity::Error => "ERROR",
+        }
+    }
+}
+
+/// An entry of the message log of the session.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub time: DateTime<Local>,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Message {
+    pub fn is_toast(&self, now: &DateTime<Local>) -> bool {
+        (*now - self.time).num_seconds() < TOAST_SECONDS
+    }
+}
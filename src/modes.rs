@@ -0,0 +1,96 @@
+/// A mode of transport that can be included in or left out of the routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportMode {
+    /// Title of the option box, e.g. `U-Bahn`
+    pub name: &'static str,
+    /// Label in the compact options row of narrow terminals
+    pub short: &'static str,
+    /// Name on the command line, e.g. `--no bus`
+    pub key: &'static str,
+    /// Transport type as named by the MVG API
+    pub api_name: &'static str,
+    /// Whether the mode is included when the app starts
+    pub default: bool,
+}
+
+/// All modes in the order of the option boxes. The toggles, the focus
+/// navigation and the queries follow this list.
+pub const MODES: &[TransportMode] = &[
+    TransportMode {
+        name: "U-Bahn",
+        short: "U",
+        key: "ubahn",
+        api_name: "UBAHN",
+        default: true,
+    },
+    TransportMode {
+        name: "S-Bahn",
+        short: "S",
+        key: "sbahn",
+        api_name: "SBAHN",
+        default: true,
+    },
+    TransportMode {
+        name: "Tram",
+        short: "T",
+        key: "tram",
+        api_name: "TRAM",
+        default: true,
+    },
+    TransportMode {
+        name: "Bus",
+        short: "B",
+        key: "bus",
+        api_name: "BUS",
+        default: true,
+    },
+    TransportMode {
+        name: "Regio",
+        short: "R",
+        key: "regional",
+        api_name: "BAHN",
+        default: true,
+    },
+    TransportMode {
+        name: "Reg. Bus",
+        short: "RB",
+        key: "regional-bus",
+        api_name: "REGIONAL_BUS",
+        default: true,
+    },
+    // Has to be booked in advance, so only on request
+    TransportMode {
+        name: "Ruftaxi",
+        short: "RT",
+        key: "ruftaxi",
+        api_name: "RUFTAXI",
+        default: false,
+    },
+];
+
+/// Whether each mode of `MODES` is included by default.
+pub fn default_modes() -> Vec<bool> {
+    MODES.iter().map(|mode| mode.default).collect()
+}
+
+/// MVG API names of the included modes, `included` is indexed like `MODES`.
+pub fn transport_types(included: &[bool]) -> Vec<String> {
+    MODES
+        .iter()
+        .zip(included)
+        .filter(|(_, on)| **on)
+        .map(|(mode, _)| mode.api_name.to_string())
+        .collect()
+}
+
+/// Looks up a mode by its command line name, for clap.
+pub fn parse_mode(key: &str) -> Result<TransportMode, String> {
+    MODES
+        .iter()
+        .find(|mode| mode.key.eq_ignore_ascii_case(key))
+        .copied()
+        .ok_or_else(|| {
+            let keys = MODES.iter().map(|mode| mode.key).collect::<Vec<&str>>();
+            format!("unknown mode, expected one of {}", keys.join(", "))
+        })
+}
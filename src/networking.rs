@@ -1,24 +1,404 @@
 use anyhow::Result;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
+use serde::Deserialize;
+use serde_with::{serde_as, TimestampMilliSeconds};
+use std::collections::HashSet;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::Mutex;
+use tokio::task::JoinSet;
 
 use crate::app::App;
-use mvg_api::{get_routes, get_station, Location};
+use crate::gps::{current_position, is_here};
+use crate::itinerary::{arrival, plan_trip, Stop};
+use crate::messages::Severity;
+use crate::routes_view::{Avoid, Preferences};
+use mvg_api::routes::Connection;
 
+#[derive(Clone)]
 pub struct RoutesParams {
     pub from: String,
     pub to: String,
     pub time: DateTime<Local>,
     pub arrival: bool,
-    pub include_ubahn: bool,
-    pub include_bus: bool,
-    pub include_tram: bool,
-    pub include_sbahn: bool,
+    /// Transport types as named by the MVG API, see `MODES`
+    pub transport_types: Vec<String>,
+    pub avoid: Avoid,
+    pub preferences: Preferences,
+    /// Address of gpsd for the `here` pseudo-station
+    pub gpsd: String,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Departure {
+    #[serde_as(as = "TimestampMilliSeconds<i64>")]
+    pub planned_departure_time: DateTime<Local>,
+    #[serde(default)]
+    pub realtime: bool,
+    pub delay_in_minutes: Option<i64>,
+    pub transport_type: String,
+    pub label: String,
+    pub destination: String,
+    #[serde(default)]
+    pub cancelled: bool,
+    pub platform: Option<i64>,
+    #[serde(default)]
+    pub messages: Vec<String>,
+}
+
+/// A line named in a disruption message.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisruptionLine {
+    pub label: String,
+    pub transport_type: String,
+}
+
+/// A service message of the MVG, e.g. an incident or construction work.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disruption {
+    pub title: String,
+    /// Full text as HTML, see `disruptions::plain_text`
+    #[serde(default)]
+    pub description: String,
+    #[serde_as(as = "Option<TimestampMilliSeconds<i64>>")]
+    pub valid_from: Option<DateTime<Local>>,
+    #[serde_as(as = "Option<TimestampMilliSeconds<i64>>")]
+    pub valid_to: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub lines: Vec<DisruptionLine>,
+}
+
+pub struct DeparturesParams {
+    pub station: String,
+    pub limit: usize,
+    /// Transport types as named by the MVG API, see `MODES`. Empty means all.
+    pub transport_types: Vec<String>,
 }
 
 pub enum IoEvent {
     GetRoutes(RoutesParams),
+    /// A trip over the stops of the via field, see `plan_trip`
+    PlanTrip(RoutesParams, Vec<Stop>),
+    /// Stations near a place and the gpsd address for `here`, see `fetch_nearby`
+    GetNearby(String, String),
+    GetDisruptions,
+}
+
+/// A start, destination or departure station, address or point of interest
+/// that the MVG does not know.
+#[derive(Debug)]
+pub struct StationNotFound(pub String);
+
+impl fmt::Display for StationNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Not found: {}", self.0)
+    }
+}
+
+impl std::error::Error for StationNotFound {}
+
+/// A result of the location search: a station, address or point of interest.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoundLocation {
+    /// `STATION`, `ADDRESS` or `POI`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    /// Only set for stations
+    pub global_id: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Start or destination of a route, either a station or a place that is
+/// reached on foot.
+#[derive(Debug, Clone, PartialEq)]
+enum Place {
+    Station(String),
+    Coordinates(f64, f64),
+}
+
+impl Place {
+    /// Query parameters for the connection API, `prefix` is `origin` or
+    /// `destination`.
+    fn query(&self, prefix: &str) -> Vec<(String, String)> {
+        match self {
+            Place::Station(id) => vec![(format!("{}StationGlobalId", prefix), id.clone())],
+            Place::Coordinates(lat, lon) => vec![
+                (format!("{}Latitude", prefix), lat.to_string()),
+                (format!("{}Longitude", prefix), lon.to_string()),
+            ],
+        }
+    }
+}
+
+/// Parses raw coordinates like `48.1374, 11.5755`.
+pub fn parse_coordinates(text: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = text.split_once(',')?;
+    let lat = lat.trim().parse::<f64>().ok()?;
+    let lon = lon.trim().parse::<f64>().ok()?;
+    match (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+        true => Some((lat, lon)),
+        false => None,
+    }
+}
+
+pub async fn search_locations(query: &str) -> Result<Vec<FoundLocation>> {
+    let locations = reqwest::Client::new()
+        .get("https://www.mvg.de/api/fib/v2/location")
+        .query(&[("query", query)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<FoundLocation>>()
+        .await?;
+    Ok(locations)
+}
+
+/// Coordinates of a station, address, point of interest, raw coordinates or
+/// `here`.
+pub async fn resolve_position(name: &str, gpsd: &str) -> Result<(f64, f64)> {
+    if is_here(name) {
+        return current_position(gpsd).await;
+    }
+    if let Some(position) = parse_coordinates(name) {
+        return Ok(position);
+    }
+    let locations = search_locations(name).await?;
+    match locations.first() {
+        Some(location) => Ok((location.latitude, location.longitude)),
+        None => Err(StationNotFound(name.to_string()).into()),
+    }
+}
+
+/// A station near a position, as returned by the MVG.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NearbyStation {
+    pub name: String,
+    pub global_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub distance_in_meters: u32,
+    /// The next departures, filled in by `fetch_nearby`
+    #[serde(default)]
+    pub departures: Vec<Departure>,
+}
+
+impl NearbyStation {
+    /// Labels of the lines leaving soon, sorted and without duplicates.
+    pub fn lines(&self) -> Vec<&str> {
+        let mut lines = self
+            .departures
+            .iter()
+            .map(|dep| dep.label.as_str())
+            .collect::<Vec<&str>>();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+}
+
+/// The stations closest to a position, the nearest first.
+pub async fn fetch_nearby_stations(lat: f64, lon: f64) -> Result<Vec<NearbyStation>> {
+    let mut stations = reqwest::Client::new()
+        .get("https://www.mvg.de/api/fib/v2/station/nearby")
+        .query(&[("latitude", lat), ("longitude", lon)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<NearbyStation>>()
+        .await?;
+    stations.sort_by_key(|station| station.distance_in_meters);
+    Ok(stations)
+}
+
+/// How many stations the nearby view lists.
+pub const NEARBY_STATIONS: usize = 10;
+
+/// How many departures are fetched per nearby station, enough to see most of
+/// the lines serving it.
+const NEARBY_DEPARTURES: usize = 30;
+
+/// The `limit` stations closest to a station, address, coordinates or `here`,
+/// each with its next departures. The departures are fetched concurrently, a
+/// station whose board fails is listed without them.
+pub async fn fetch_nearby(location: &str, gpsd: &str, limit: usize) -> Result<Vec<NearbyStation>> {
+    let (lat, lon) = resolve_position(location, gpsd).await?;
+    let mut stations = fetch_nearby_stations(lat, lon).await?;
+    stations.truncate(limit);
+    let mut boards = JoinSet::new();
+    for (i, station) in stations.iter().enumerate() {
+        let id = station.global_id.clone();
+        boards.spawn(async move { (i, query_departures(&id, NEARBY_DEPARTURES, &[]).await) });
+    }
+    while let Some(board) = boards.join_next().await {
+        if let Ok((i, Ok(departures))) = board {
+            stations[i].departures = departures;
+        }
+    }
+    Ok(stations)
+}
+
+/// Id of the station best matching `name`. An address or point of interest
+/// as best match is not found, rather than showing some other station.
+async fn station_id(name: &str) -> Result<String> {
+    let locations = search_locations(name).await?;
+    locations
+        .into_iter()
+        .next()
+        .and_then(|location| location.global_id)
+        .ok_or_else(|| StationNotFound(name.to_string()).into())
+}
+
+/// Resolves a start or destination through the location search. The best
+/// match is used, stations by their id and addresses and points of interest
+/// by their coordinates. `here` is the current position from gpsd.
+async fn resolve_place(name: &str, gpsd: &str) -> Result<Place> {
+    if is_here(name) {
+        let (lat, lon) = current_position(gpsd).await?;
+        return Ok(Place::Coordinates(lat, lon));
+    }
+    if let Some((lat, lon)) = parse_coordinates(name) {
+        return Ok(Place::Coordinates(lat, lon));
+    }
+    let locations = search_locations(name).await?;
+    match locations.into_iter().next() {
+        Some(FoundLocation {
+            global_id: Some(id),
+            ..
+        }) => Ok(Place::Station(id)),
+        Some(location) => Ok(Place::Coordinates(location.latitude, location.longitude)),
+        None => Err(StationNotFound(name.to_string()).into()),
+    }
+}
+
+/// How often `fetch_routes` asks for later connections when too many of them
+/// were left out.
+const MAX_REQUERIES: usize = 3;
+
+async fn query_routes(
+    from: &Place,
+    to: &Place,
+    rp: &RoutesParams,
+    time: DateTime<Local>,
+) -> Result<Vec<Connection>> {
+    let mut query = from.query("origin");
+    query.extend(to.query("destination"));
+    query.extend([
+        (
+            "routingDateTime".to_string(),
+            time.with_timezone(&Utc)
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string(),
+        ),
+        (
+            "routingDateTimeIsArrival".to_string(),
+            rp.arrival.to_string(),
+        ),
+        ("transportTypes".to_string(), rp.transport_types.join(",")),
+    ]);
+    query.extend(rp.preferences.query());
+    let routes = reqwest::Client::new()
+        .get("https://www.mvg.de/api/fib/v2/connection")
+        .query(&query)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<Connection>>()
+        .await?;
+    Ok(routes)
+}
+
+/// Fetches the connections door to door and drops the ones using an avoided
+/// line or station or not fitting the preferences. If fewer than
+/// `avoid.min_connections` are left, the connections departing after the last
+/// one are fetched as well, up to `MAX_REQUERIES` times. Arrival times are
+/// never re-queried, later connections would arrive too late.
+pub async fn fetch_routes(rp: &RoutesParams) -> Result<Vec<Connection>> {
+    let from = resolve_place(&rp.from, &rp.gpsd).await?;
+    let to = resolve_place(&rp.to, &rp.gpsd).await?;
+    let mut routes = Vec::new();
+    let mut seen = HashSet::new();
+    let mut time = rp.time;
+    for _ in 0..=MAX_REQUERIES {
+        let batch = query_routes(&from, &to, rp, time).await?;
+        let last = batch
+            .iter()
+            .map(|c| c.parts[0].from.planned_departure)
+            .max();
+        let mut new = false;
+        for conn in batch {
+            if seen.insert(conn.unique_id) {
+                new = true;
+                if rp.avoid.allows(&conn) && rp.preferences.allows(&conn) {
+                    routes.push(conn);
+                }
+            }
+        }
+        match last {
+            Some(last) if new && !rp.arrival && routes.len() < rp.avoid.min_connections => {
+                time = last
+            }
+            _ => break,
+        }
+    }
+    Ok(routes)
+}
+
+pub async fn fetch_departures(dp: &DeparturesParams) -> Result<Vec<Departure>> {
+    let id = station_id(&dp.station).await?;
+    query_departures(&id, dp.limit, &dp.transport_types).await
+}
+
+async fn query_departures(
+    id: &str,
+    limit: usize,
+    transport_types: &[String],
+) -> Result<Vec<Departure>> {
+    let mut query = vec![
+        ("globalId", id.to_string()),
+        ("limit", limit.to_string()),
+        ("offsetInMinutes", "0".to_string()),
+    ];
+    if !transport_types.is_empty() {
+        query.push(("transportTypes", transport_types.join(",")));
+    }
+    let departures = reqwest::Client::new()
+        .get("https://www.mvg.de/api/fib/v2/departure")
+        .query(&query)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<Departure>>()
+        .await?;
+    Ok(departures)
+}
+
+/// All current disruptions and service messages of the MVG.
+pub async fn fetch_disruptions() -> Result<Vec<Disruption>> {
+    let disruptions = reqwest::Client::new()
+        .get("https://www.mvg.de/api/bgw-pt/v3/messages")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<Disruption>>()
+        .await?;
+    Ok(disruptions)
+}
+
+fn avoiding(avoid: &Avoid) -> String {
+    match avoid.is_empty() {
+        true => String::new(),
+        false => format!(" avoiding {}", avoid.describe()),
+    }
 }
 
 pub async fn start_tokio(
@@ -28,35 +408,93 @@ pub async fn start_tokio(
     while let Some(io_event) = io_rx.recv().await {
         match io_event {
             IoEvent::GetRoutes(rp) => {
-                let from = get_station(&rp.from).await?;
-                let from_id = if let Location::Station(x) = &from[0] {
-                    x.global_id.clone()
-                } else {
-                    "".to_string()
-                };
-                let to = get_station(&rp.to).await?;
-                let to_id = if let Location::Station(x) = &to[0] {
-                    x.global_id.clone()
-                } else {
-                    "".to_string()
-                };
-                let routes = get_routes(
-                    &from_id,
-                    &to_id,
-                    Some(rp.time),
-                    Some(rp.arrival),
-                    Some(rp.include_ubahn),
-                    Some(rp.include_bus),
-                    Some(rp.include_tram),
-                    Some(rp.include_sbahn),
-                    None,
-                )
-                .await?;
+                let started = Instant::now();
+                // The disruptions are only for the badges in the routes table,
+                // the connections are shown without them
+                let (result, disruptions) = tokio::join!(fetch_routes(&rp), fetch_disruptions());
 
                 // Acquire a lock on the App Mutex and mutate the state
                 let mut app = app.lock().await;
-                app.routes = routes;
                 app.show_fetch_popup = false;
+                match disruptions {
+                    Ok(disruptions) => app.disruptions = disruptions,
+                    Err(e) => app.log(
+                        Severity::Warning,
+                        format!("Could not update the disruptions: {:#}", e),
+                    ),
+                }
+                match result {
+                    Ok(routes) if routes.is_empty() => {
+                        let msg = format!("No connections found{}", avoiding(&rp.avoid));
+                        app.log(Severity::Warning, msg);
+                        app.set_routes(routes);
+                    }
+                    Ok(routes) => {
+                        let msg = format!(
+                            "Fetched {} connections{} in {} ms",
+                            routes.len(),
+                            avoiding(&rp.avoid),
+                            started.elapsed().as_millis()
+                        );
+                        app.log(Severity::Info, msg);
+                        app.set_routes(routes);
+                    }
+                    Err(e) if e.is::<StationNotFound>() => {
+                        app.log(Severity::Warning, e.to_string())
+                    }
+                    Err(e) => app.log(Severity::Error, format!("Fetching failed: {:#}", e)),
+                }
+            }
+            IoEvent::PlanTrip(rp, via) => {
+                let result = plan_trip(&rp, &via).await;
+
+                let mut app = app.lock().await;
+                app.show_fetch_popup = false;
+                match result {
+                    Ok(plan) => {
+                        let msg = format!(
+                            "Planned trip in {} parts, arriving at {}",
+                            plan.len(),
+                            plan.last().map(arrival).unwrap_or(rp.time).format("%H:%M")
+                        );
+                        app.log(Severity::Info, msg);
+                        app.set_plan(via, plan);
+                    }
+                    Err(e) if e.is::<StationNotFound>() => {
+                        app.log(Severity::Warning, e.to_string())
+                    }
+                    Err(e) => app.log(Severity::Error, format!("Planning failed: {:#}", e)),
+                }
+            }
+            IoEvent::GetNearby(location, gpsd) => {
+                let result = fetch_nearby(&location, &gpsd, NEARBY_STATIONS).await;
+
+                let mut app = app.lock().await;
+                app.show_fetch_popup = false;
+                match result {
+                    Ok(stations) if stations.is_empty() => {
+                        app.log(Severity::Warning, format!("No stations near {}", location))
+                    }
+                    Ok(stations) => {
+                        let msg = format!("Found {} stations near {}", stations.len(), location);
+                        app.log(Severity::Info, msg);
+                        app.set_nearby(location, stations);
+                    }
+                    Err(e) if e.is::<StationNotFound>() => {
+                        app.log(Severity::Warning, e.to_string())
+                    }
+                    Err(e) => app.log(Severity::Error, format!("Fetching failed: {:#}", e)),
+                }
+            }
+            IoEvent::GetDisruptions => {
+                let result = fetch_disruptions().await;
+
+                let mut app = app.lock().await;
+                app.show_fetch_popup = false;
+                match result {
+                    Ok(disruptions) => app.set_disruptions(disruptions),
+                    Err(e) => app.log(Severity::Error, format!("Fetching failed: {:#}", e)),
+                }
             }
         }
     }
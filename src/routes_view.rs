@@ -0,0 +1,315 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use mvg_api::routes::{Connection, ConnectionPart, Station};
+use serde::Deserialize;
+
+/// Order of the connections in the routes table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    #[default]
+    Departure,
+    Arrival,
+    Duration,
+    Changes,
+    Walking,
+    Delay,
+}
+
+impl SortKey {
+    pub fn next(self) -> SortKey {
+        match self {
+            SortKey::Departure => SortKey::Arrival,
+            SortKey::Arrival => SortKey::Duration,
+            SortKey::Duration => SortKey::Changes,
+            SortKey::Changes => SortKey::Walking,
+            SortKey::Walking => SortKey::Delay,
+            SortKey::Delay => SortKey::Departure,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            SortKey::Departure => "departure",
+            SortKey::Arrival => "arrival",
+            SortKey::Duration => "duration",
+            SortKey::Changes => "changes",
+            SortKey::Walking => "walking time",
+            SortKey::Delay => "delay",
+        }
+    }
+
+    /// Column of `ROUTES_HEADER` the key belongs to, if any.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            SortKey::Departure | SortKey::Arrival => Some(0),
+            SortKey::Duration => Some(2),
+            SortKey::Changes | SortKey::Walking => Some(3),
+            SortKey::Delay => Some(4),
+        }
+    }
+
+    fn value(&self, conn: &Connection) -> i64 {
+        let origin = &conn.parts[0].from;
+        let destination = &conn.parts[conn.parts.len() - 1].to;
+        match self {
+            SortKey::Departure => origin.planned_departure.timestamp(),
+            SortKey::Arrival => destination.planned_departure.timestamp(),
+            SortKey::Duration => {
+                (destination.planned_departure - origin.planned_departure).num_minutes()
+            }
+            SortKey::Changes => {
+                let rides = conn.parts.iter().filter(|cp| !is_walk(cp)).count() as i64;
+                (rides - 1).max(0)
+            }
+            SortKey::Walking => conn
+                .parts
+                .iter()
+                .filter(|cp| is_walk(cp))
+                .map(|cp| (cp.to.planned_departure - cp.from.planned_departure).num_minutes())
+                .sum(),
+            SortKey::Delay => origin.departure_delay_in_minutes.unwrap_or(0),
+        }
+    }
+}
+
+pub fn is_walk(cp: &ConnectionPart) -> bool {
+    cp.line.label == "FOOTWAY"
+}
+
+/// Sorts the connections stably, so connections with the same value keep
+/// their order by departure.
+pub fn sort_routes(routes: &mut [Connection], key: SortKey, descending: bool) {
+    routes.sort_by_key(|conn| SortKey::Departure.value(conn));
+    if descending {
+        routes.sort_by_key(|conn| std::cmp::Reverse(key.value(conn)));
+    } else {
+        routes.sort_by_key(|conn| key.value(conn));
+    }
+}
+
+/// Whether a connection matches the filter text. Every word of the filter has
+/// to be contained in a line label or station name of the connection, case
+/// insensitive. Words starting with `!` exclude connections instead, e.g.
+/// `!U3` for all connections without the U3.
+pub fn matches_filter(conn: &Connection, filter: &str) -> bool {
+    filter.split_whitespace().all(|word| {
+        let (exclude, word) = match word.strip_prefix('!') {
+            Some(word) => (true, word),
+            None => (false, word),
+        };
+        if word.is_empty() {
+            return true;
+        }
+        let word = word.to_lowercase();
+        let found = conn.parts.iter().any(|cp| {
+            (!is_walk(cp) && cp.line.label.to_lowercase().contains(&word))
+                || cp.from.name.to_lowercase().contains(&word)
+                || cp.to.name.to_lowercase().contains(&word)
+        });
+        found != exclude
+    })
+}
+
+/// Lines and stations to leave out of the fetched connections, e.g. during
+/// construction work.
+///
+/// ```toml
+/// [avoid]
+/// lines = ["U6"]
+/// stations = ["Marienplatz"]
+/// min_connections = 4
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Avoid {
+    /// Line labels, e.g. `U6` or `S8`
+    pub lines: Vec<String>,
+    /// Stations that are neither changed at nor passed through
+    pub stations: Vec<String>,
+    /// Fetch later connections until at least this many are left, see `fetch_routes`
+    pub min_connections: usize,
+}
+
+impl Avoid {
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.stations.is_empty()
+    }
+
+    /// Whether a connection uses none of the lines and stations, compared case
+    /// insensitive. Intermediate stops count as well, since the train still
+    /// passes them.
+    pub fn allows(&self, conn: &Connection) -> bool {
+        conn.parts.iter().all(|cp| {
+            let line_ok = is_walk(cp)
+                || !self
+                    .lines
+                    .iter()
+                    .any(|l| l.to_lowercase() == cp.line.label.to_lowercase());
+            let stations_ok = std::iter::once(&cp.from)
+                .chain(&cp.intermediate_stops)
+                .chain(std::iter::once(&cp.to))
+                .all(|station| {
+                    !self
+                        .stations
+                        .iter()
+                        .any(|s| s.to_lowercase() == station.name.to_lowercase())
+                });
+            line_ok && stations_ok
+        })
+    }
+
+    /// The avoided lines and stations, e.g. `U6, Marienplatz`.
+    pub fn describe(&self) -> String {
+        self.lines
+            .iter()
+            .chain(&self.stations)
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// How fast changes on foot are made, relative to a normal walking pace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WalkingSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+/// Walking pace in meters per minute at normal speed, about 4.8 km/h.
+const WALKING_PACE: f64 = 80.0;
+
+impl WalkingSpeed {
+    fn factor(&self) -> f64 {
+        match self {
+            WalkingSpeed::Slow => 1.5,
+            WalkingSpeed::Normal => 1.0,
+            WalkingSpeed::Fast => 0.75,
+        }
+    }
+
+    fn api_name(&self) -> &str {
+        match self {
+            WalkingSpeed::Slow => "SLOW",
+            WalkingSpeed::Normal => "NORMAL",
+            WalkingSpeed::Fast => "FAST",
+        }
+    }
+
+    /// Minutes to walk `meters` at this speed, rounded up.
+    pub fn walking_minutes(&self, meters: u32) -> u32 {
+        (meters as f64 / WALKING_PACE * self.factor()).ceil() as u32
+    }
+}
+
+/// Routing preferences. Most of them are sent along with the connection query,
+/// see `Preferences::query`, and checked again on the fetched connections, so
+/// connections that do not fit are left out even where the API ignores them.
+///
+/// ```toml
+/// [preferences]
+/// walking_speed = "slow"
+/// step_free = true  # elevator outage filter only
+/// max_walk = 400
+/// min_change_time = 5
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Changes need the time to walk between the stations at this speed
+    pub walking_speed: WalkingSpeed,
+    /// Leave out connections with a reported elevator outage on the way. This
+    /// is only a filter on the messages, it does not route step-free.
+    pub step_free: bool,
+    /// Longest walk in meters, as the crow flies
+    pub max_walk: Option<u32>,
+    /// Minutes between arriving with one line and leaving with the next
+    pub min_change_time: Option<u32>,
+}
+
+/// Words of disruption messages about broken elevators.
+const ELEVATOR_WORDS: [&str; 3] = ["aufzug", "elevator", "lift"];
+
+impl Preferences {
+    /// Query parameters of the connection API for the preferences that differ
+    /// from its defaults. `step_free` is only checked locally, see `allows`.
+    pub fn query(&self) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+        if self.walking_speed != WalkingSpeed::Normal {
+            query.push((
+                "walkingSpeed".to_string(),
+                self.walking_speed.api_name().to_string(),
+            ));
+        }
+        if let Some(max_walk) = self.max_walk {
+            query.push(("maxWalkDistance".to_string(), max_walk.to_string()));
+        }
+        if let Some(min_change_time) = self.min_change_time {
+            query.push(("changeTime".to_string(), min_change_time.to_string()));
+        }
+        query
+    }
+
+    /// Whether a connection can be taken with these preferences.
+    pub fn allows(&self, conn: &Connection) -> bool {
+        if self.step_free
+            && conn.parts.iter().flat_map(|cp| &cp.messages).any(|msg| {
+                let msg = msg.to_lowercase();
+                ELEVATOR_WORDS.iter().any(|word| msg.contains(word))
+            })
+        {
+            return false;
+        }
+        if let Some(max_walk) = self.max_walk {
+            let too_far = conn
+                .parts
+                .iter()
+                .filter(|cp| is_walk(cp))
+                .any(|cp| distance(&cp.from, &cp.to) > max_walk as f64);
+            if too_far {
+                return false;
+            }
+        }
+        self.changes_fit(conn)
+    }
+
+    /// Whether every change leaves enough time for the minimum change time and
+    /// for the walks in between. A walk takes the time of the MVG, which already
+    /// includes the walking speed if the API honours it, but at least the
+    /// distance as the crow flies at the walking speed.
+    fn changes_fit(&self, conn: &Connection) -> bool {
+        let min_change = self.min_change_time.unwrap_or(0) as f64;
+        let mut last_arrival: Option<DateTime<Local>> = None;
+        let mut walking: f64 = 0.0;
+        for cp in &conn.parts {
+            if is_walk(cp) {
+                let minutes = (cp.to.planned_departure - cp.from.planned_departure).num_minutes();
+                let meters = distance(&cp.from, &cp.to).round() as u32;
+                walking += minutes.max(self.walking_speed.walking_minutes(meters) as i64) as f64;
+                continue;
+            }
+            if let Some(arrival) = last_arrival {
+                let window = (cp.from.planned_departure - arrival).num_minutes() as f64;
+                if !cp.no_change_required && window < walking.max(min_change) {
+                    return false;
+                }
+            }
+            last_arrival = Some(cp.to.planned_departure);
+            walking = 0.0;
+        }
+        true
+    }
+}
+
+/// Distance between two stations in meters, as the crow flies.
+pub fn distance(a: &Station, b: &Station) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (lat_a, lat_b) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.longitude - a.longitude).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
@@ -0,0 +1,193 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use mvg_api::routes::Connection;
+use serde_json::json;
+
+use crate::config::{Config, Favorite};
+use crate::modes::{default_modes, transport_types};
+use crate::networking::{
+    fetch_departures, fetch_routes, Departure, DeparturesParams, RoutesParams,
+};
+use crate::ui_elements::{departure_cells, route_cells};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum StatusFormat {
+    Waybar,
+    I3blocks,
+    Tmux,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusClass {
+    Ok,
+    Delayed,
+    Disrupted,
+}
+
+impl StatusClass {
+    fn name(&self) -> &str {
+        match self {
+            StatusClass::Ok => "ok",
+            StatusClass::Delayed => "delayed",
+            StatusClass::Disrupted => "disrupted",
+        }
+    }
+
+    fn color(&self) -> &str {
+        match self {
+            StatusClass::Ok => "#00ff00",
+            StatusClass::Delayed => "#ffff00",
+            StatusClass::Disrupted => "#ff0000",
+        }
+    }
+
+    fn tmux_style(&self) -> &str {
+        match self {
+            StatusClass::Ok => "#[fg=green]",
+            StatusClass::Delayed => "#[fg=yellow]",
+            StatusClass::Disrupted => "#[fg=red]",
+        }
+    }
+}
+
+/// One refresh of the status bar: a short text, a longer tooltip and a class
+/// that status bars can use for styling.
+#[derive(Debug)]
+pub struct Status {
+    pub text: String,
+    pub tooltip: String,
+    pub class: StatusClass,
+}
+
+impl Status {
+    pub fn render(&self, format: StatusFormat) -> String {
+        match format {
+            StatusFormat::Waybar => json!({
+                "text": self.text,
+                "tooltip": self.tooltip,
+                "class": self.class.name(),
+                "alt": self.class.name(),
+            })
+            .to_string(),
+            StatusFormat::I3blocks => json!({
+                "full_text": self.text,
+                "short_text": self.text,
+                "color": self.class.color(),
+            })
+            .to_string(),
+            StatusFormat::Tmux => format!("{}{}#[default]", self.class.tmux_style(), self.text),
+        }
+    }
+}
+
+fn connection_class(conn: &Connection) -> StatusClass {
+    match conn.parts[0].from.departure_delay_in_minutes {
+        _ if conn.parts.iter().any(|cp| !cp.messages.is_empty()) => StatusClass::Disrupted,
+        Some(d) if d > 0 => StatusClass::Delayed,
+        _ => StatusClass::Ok,
+    }
+}
+
+fn trip_status(routes: &[Connection], now: &DateTime<Local>) -> Status {
+    let conn = match routes.first() {
+        Some(conn) => conn,
+        None => {
+            return Status {
+                text: "no connection".to_string(),
+                tooltip: "".to_string(),
+                class: StatusClass::Disrupted,
+            }
+        }
+    };
+    // Cells are in the order of ROUTES_HEADER: TIME, IN, DURATION, LINES, DELAY, INFO
+    let cells = route_cells(conn, now, None);
+    // Early departures keep their minus sign, only delays get a plus
+    let text = match conn.parts[0].from.departure_delay_in_minutes {
+        Some(d) if d > 0 => format!("{} {} (+{})", cells[3], cells[0], d),
+        Some(d) if d < 0 => format!("{} {} ({})", cells[3], cells[0], d),
+        _ => format!("{} {}", cells[3], cells[0]),
+    };
+    let tooltip = routes
+        .iter()
+        .map(|conn| {
+            let cells = route_cells(conn, now, None);
+            format!("{}  {}  {}", cells[0], cells[3], cells[4])
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    Status {
+        text,
+        tooltip,
+        class: connection_class(conn),
+    }
+}
+
+fn departure_class(dep: &Departure) -> StatusClass {
+    match dep.delay_in_minutes {
+        _ if dep.cancelled => StatusClass::Disrupted,
+        Some(d) if d > 0 => StatusClass::Delayed,
+        _ => StatusClass::Ok,
+    }
+}
+
+fn station_status(station: &str, departures: &[Departure], now: &DateTime<Local>) -> Status {
+    let dep = match departures.first() {
+        Some(dep) => dep,
+        None => {
+            return Status {
+                text: format!("{}: no departures", station),
+                tooltip: "".to_string(),
+                class: StatusClass::Disrupted,
+            }
+        }
+    };
+    // Cells are in the order of DEPARTURES_HEADER: TIME, IN, LINE, DESTINATION, PLATFORM, DELAY
+    let cells = departure_cells(dep, now, None);
+    let tooltip = departures
+        .iter()
+        .map(|dep| {
+            let cells = departure_cells(dep, now, None);
+            format!("{}  {}  {}  {}", cells[0], cells[2], cells[3], cells[5])
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let text = match dep.cancelled {
+        true => format!("{} {} cancelled", cells[2], cells[3]),
+        false => format!("{} {} in {}'", cells[2], cells[3], cells[1]),
+    };
+    Status {
+        text,
+        tooltip,
+        class: departure_class(dep),
+    }
+}
+
+pub async fn fetch_status(favorite: &Favorite, config: &Config) -> Result<Status> {
+    match favorite {
+        Favorite::Trip { from, to } => {
+            let now = Local::now();
+            let routes = fetch_routes(&RoutesParams {
+                from: from.clone(),
+                to: to.clone(),
+                time: now,
+                arrival: false,
+                transport_types: transport_types(&default_modes()),
+                avoid: config.avoid.clone(),
+                preferences: config.preferences.clone(),
+                gpsd: config.gpsd_address(),
+            })
+            .await?;
+            Ok(trip_status(&routes, &now))
+        }
+        Favorite::Station { station } => {
+            let departures = fetch_departures(&DeparturesParams {
+                station: station.clone(),
+                limit: 5,
+                transport_types: Vec::new(),
+            })
+            .await?;
+            Ok(station_status(station, &departures, &Local::now()))
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tui::style::Color;
+
+/// Colours used throughout the UI. Line colours in the timeline and map are
+/// the official MVG colours and not part of the theme.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// Border or text of the focused element
+    pub focus: Color,
+    /// Focused element while editing it or navigating the table
+    pub editing: Color,
+    pub toggle_on: Color,
+    pub toggle_off: Color,
+    /// Column headers of tables
+    pub header: Color,
+    pub help: Color,
+    pub popup_fg: Color,
+    pub popup_bg: Color,
+    pub error: Color,
+    /// Info messages in toasts and the message log
+    pub info: Color,
+    pub warning: Color,
+    /// Less important content, like waiting times in the timeline
+    pub muted: Color,
+    /// Intermediate stops on the map
+    pub map_stop: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            focus: Color::Blue,
+            editing: Color::Yellow,
+            toggle_on: Color::Green,
+            toggle_off: Color::Red,
+            header: Color::Magenta,
+            help: Color::Cyan,
+            popup_fg: Color::Black,
+            popup_bg: Color::Blue,
+            error: Color::Red,
+            info: Color::Cyan,
+            warning: Color::Yellow,
+            muted: Color::DarkGray,
+            map_stop: Color::White,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            focus: Color::Blue,
+            editing: Color::Rgb(175, 95, 0),
+            toggle_on: Color::Rgb(0, 128, 0),
+            toggle_off: Color::Rgb(175, 0, 0),
+            header: Color::Magenta,
+            help: Color::Rgb(0, 95, 135),
+            popup_fg: Color::White,
+            popup_bg: Color::Blue,
+            error: Color::Red,
+            info: Color::Rgb(0, 95, 135),
+            warning: Color::Rgb(175, 95, 0),
+            muted: Color::Gray,
+            map_stop: Color::Black,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            focus: Color::LightCyan,
+            editing: Color::LightYellow,
+            toggle_on: Color::LightGreen,
+            toggle_off: Color::LightRed,
+            header: Color::White,
+            help: Color::White,
+            popup_fg: Color::Black,
+            popup_bg: Color::White,
+            error: Color::LightRed,
+            info: Color::LightCyan,
+            warning: Color::LightYellow,
+            muted: Color::Gray,
+            map_stop: Color::White,
+        }
+    }
+
+    /// Based on the Okabe-Ito palette, which stays distinguishable with the
+    /// common forms of colour blindness. Toggles use blue and orange instead of
+    /// green and red.
+    pub fn colorblind() -> Self {
+        Theme {
+            focus: Color::Rgb(0, 114, 178),
+            editing: Color::Rgb(240, 228, 66),
+            toggle_on: Color::Rgb(86, 180, 233),
+            toggle_off: Color::Rgb(230, 159, 0),
+            header: Color::Rgb(204, 121, 167),
+            help: Color::Rgb(86, 180, 233),
+            popup_fg: Color::Black,
+            popup_bg: Color::Rgb(0, 114, 178),
+            error: Color::Rgb(213, 94, 0),
+            info: Color::Rgb(86, 180, 233),
+            warning: Color::Rgb(230, 159, 0),
+            muted: Color::DarkGray,
+            map_stop: Color::White,
+        }
+    }
+
+    fn preset(name: &str) -> Result<Theme> {
+        match name {
+            "dark" => Ok(Theme::dark()),
+            "light" => Ok(Theme::light()),
+            "high-contrast" => Ok(Theme::high_contrast()),
+            "colorblind" => Ok(Theme::colorblind()),
+            _ => Err(anyhow!("Unknown theme preset: {}", name)),
+        }
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        match name {
+            "focus" => Some(&mut self.focus),
+            "editing" => Some(&mut self.editing),
+            "toggle_on" => Some(&mut self.toggle_on),
+            "toggle_off" => Some(&mut self.toggle_off),
+            "header" => Some(&mut self.header),
+            "help" => Some(&mut self.help),
+            "popup_fg" => Some(&mut self.popup_fg),
+            "popup_bg" => Some(&mut self.popup_bg),
+            "error" => Some(&mut self.error),
+            "info" => Some(&mut self.info),
+            "warning" => Some(&mut self.warning),
+            "muted" => Some(&mut self.muted),
+            "map_stop" => Some(&mut self.map_stop),
+            _ => None,
+        }
+    }
+
+    /// Builds the theme from the `[theme]` table of the config file: a preset
+    /// and optionally single colours overriding it.
+    ///
+    /// ```toml
+    /// [theme]
+    /// preset = "colorblind"
+    /// focus = "#ff8800"
+    /// ```
+    pub fn from_config(config: &ThemeConfig) -> Result<Theme> {
+        let mut theme = match &config.preset {
+            Some(name) => Theme::preset(name)?,
+            None => Theme::default(),
+        };
+        for (name, value) in &config.colors {
+            let color = theme
+                .color_mut(name)
+                .ok_or_else(|| anyhow!("Unknown theme colour: {}", name))?;
+            *color = parse_color(value)?;
+        }
+        Ok(theme)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ThemeConfig {
+    pub preset: Option<String>,
+    #[serde(flatten)]
+    pub colors: BTreeMap<String, String>,
+}
+
+/// Parses a colour name like `lightblue` or a hex colour like `#ff8800`.
+fn parse_color(value: &str) -> Result<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| anyhow!("Invalid colour: {}", value))
+        };
+        if hex.len() != 6 {
+            return Err(anyhow!("Invalid colour: {}", value));
+        }
+        return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    match value.to_lowercase().replace(['-', '_'], "").as_str() {
+        "reset" => Ok(Color::Reset),
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "gray" | "grey" => Ok(Color::Gray),
+        "darkgray" | "darkgrey" => Ok(Color::DarkGray),
+        "lightred" => Ok(Color::LightRed),
+        "lightgreen" => Ok(Color::LightGreen),
+        "lightyellow" => Ok(Color::LightYellow),
+        "lightblue" => Ok(Color::LightBlue),
+        "lightmagenta" => Ok(Color::LightMagenta),
+        "lightcyan" => Ok(Color::LightCyan),
+        "white" => Ok(Color::White),
+        _ => Err(anyhow!("Invalid colour: {}", value)),
+    }
+}
@@ -1,26 +1,201 @@
+use serde::Deserialize;
 use tui::{
     backend::Backend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Clear},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Clear, TableState},
     Frame,
 };
 use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    app::{App, Focus, InputMode, RoutesTableState},
+    app::{App, DetailsView, Focus, InputMode, RoutesTableState},
+    modes::MODES,
     ui_elements::{
-        arrival_paragraph, bus_paragraph, date_paragraph, desination_paragraph, details_list,
-        help_message, notifications, popup_rect, routes_table, sbahn_paragraph, start_paragraph,
-        time_paragraph, tram_paragraph, ubahn_paragraph, wrong_datetime_paragraph,
+        arrival_paragraph, date_paragraph, desination_paragraph, details_list,
+        disruptions_overview, disruptions_text, help_message, help_overlay, help_overlay_text,
+        message_log, message_log_text, minimal_cursor, minimal_line, mode_paragraph, nearby_table,
+        notifications, notifications_height, plan_list, popup_rect, route_map, routes_table,
+        routes_title, start_paragraph, time_paragraph, timeline, toasts, toasts_size,
+        toggles_paragraph, via_paragraph, wrong_datetime_paragraph,
     },
 };
 
+/// Terminals narrower than this stack the details under the routes table.
+const NARROW_WIDTH: u16 = 100;
+/// Terminals smaller than this in either direction use the one-line layout.
+const MINIMAL_WIDTH: u16 = 40;
+const MINIMAL_HEIGHT: u16 = 14;
+
+/// Arrangement of the panes, chosen from the terminal size unless fixed in the
+/// config file with `layout = "narrow"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutMode {
+    #[default]
+    Auto,
+    /// Input row, one box per option and details next to the routes table
+    Wide,
+    /// Options in a single compact row, details below the routes table and
+    /// fewer table columns
+    Narrow,
+    /// A single line with the selected or next connection
+    Minimal,
+}
+
+impl LayoutMode {
+    pub fn resolve(self, area: Rect) -> LayoutMode {
+        match self {
+            LayoutMode::Auto if area.width < MINIMAL_WIDTH || area.height < MINIMAL_HEIGHT => {
+                LayoutMode::Minimal
+            }
+            LayoutMode::Auto if area.width < NARROW_WIDTH => LayoutMode::Narrow,
+            LayoutMode::Auto => LayoutMode::Wide,
+            mode => mode,
+        }
+    }
+}
+
 pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, routes_table_state: &mut RoutesTableState) {
+    // Sorting, filtering or a new fetch can leave the selection behind the last row
+    routes_table_state.clamp(app.routes.len());
+    let layout = app.layout.resolve(f.size());
+    if layout == LayoutMode::Minimal {
+        minimal_ui(f, app, routes_table_state);
+    } else {
+        full_ui(f, app, routes_table_state, layout == LayoutMode::Narrow);
+    }
+
+    // Fetching popup
+    if app.show_fetch_popup {
+        let popup_area = popup_rect(10, 5, f.size());
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Fetching")
+            .title_alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .bg(app.theme.popup_bg)
+                    .fg(app.theme.popup_fg),
+            );
+        f.render_widget(Clear, popup_area);
+        f.render_widget(block, popup_area);
+    }
+
+    // Datetime error popups
+    if app.wrong_date {
+        let date_popup_area = popup_rect(20, 20, f.size());
+        let wrong_date_paragraph =
+            wrong_datetime_paragraph("Please enter a valid date", "Date Error", &app.theme);
+        f.render_widget(Clear, date_popup_area);
+        f.render_widget(wrong_date_paragraph, date_popup_area);
+    }
+
+    if app.wrong_time {
+        let date_popup_area = popup_rect(20, 20, f.size());
+        let wrong_time_paragraph =
+            wrong_datetime_paragraph("Please enter a valid time", "Time Error", &app.theme);
+        f.render_widget(Clear, date_popup_area);
+        f.render_widget(wrong_time_paragraph, date_popup_area);
+    }
+
+    if app.wrong_via {
+        let via_popup_area = popup_rect(40, 20, f.size());
+        let wrong_via_paragraph = wrong_datetime_paragraph(
+            "Enter stops like: Odeonsplatz +45; Giesing",
+            "Via Error",
+            &app.theme,
+        );
+        f.render_widget(Clear, via_popup_area);
+        f.render_widget(wrong_via_paragraph, via_popup_area);
+    }
+
+    // Toasts in the lower right corner, above the help bar
+    if layout != LayoutMode::Minimal && !app.toasts().is_empty() {
+        let area = f.size();
+        let (width, height) = toasts_size(app, area.width / 2);
+        if area.height > height + 3 {
+            let toasts_area = Rect {
+                x: area.width - width - 2,
+                y: area.height - height - 3,
+                width,
+                height,
+            };
+            f.render_widget(Clear, toasts_area);
+            f.render_widget(toasts(app), toasts_area);
+        }
+    }
+
+    // Nearby stations, or the departures at one of them
+    if app.show_nearby {
+        let area = f.size();
+        let mut state = TableState::default();
+        if !app.nearby_board {
+            state.select(Some(app.nearby_selected));
+        }
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(nearby_table(app), area, &mut state);
+    }
+
+    // Disruptions overview, scrolling stops at the last line
+    if app.show_disruptions {
+        let area = f.size();
+        let width = area.width.saturating_sub(2);
+        let visible = area.height.saturating_sub(2);
+        let max_scroll = (disruptions_text(app, width).height() as u16).saturating_sub(visible);
+        app.disruptions_scroll = app.disruptions_scroll.min(max_scroll);
+        f.render_widget(Clear, area);
+        f.render_widget(disruptions_overview(app, width), area);
+    }
+
+    // Message log, scrolling stops at the last line
+    if app.show_log {
+        let area = f.size();
+        let visible = area.height.saturating_sub(2);
+        let max_scroll = (message_log_text(app).height() as u16).saturating_sub(visible);
+        app.log_scroll = app.log_scroll.min(max_scroll);
+        f.render_widget(Clear, area);
+        f.render_widget(message_log(app), area);
+    }
+
+    // Help overlay, scrolling stops at the last line
+    if app.show_help {
+        let area = f.size();
+        let visible = area.height.saturating_sub(2);
+        let max_scroll = (help_overlay_text(app).height() as u16).saturating_sub(visible);
+        app.help_scroll = app.help_scroll.min(max_scroll);
+        f.render_widget(Clear, area);
+        f.render_widget(help_overlay(app), area);
+    }
+}
+
+fn minimal_ui<B: Backend>(f: &mut Frame<B>, app: &App, routes_table_state: &RoutesTableState) {
+    let area = Rect {
+        height: f.size().height.min(1),
+        ..f.size()
+    };
+    let line = minimal_line(app, routes_table_state);
+    f.render_widget(line, area);
+    if let Some(x) = minimal_cursor(app) {
+        f.set_cursor(area.x + x.min(area.width.saturating_sub(1)), area.y);
+    }
+}
+
+fn full_ui<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    routes_table_state: &mut RoutesTableState,
+    narrow: bool,
+) {
+    if app.maximized && maximized_pane(f, app, routes_table_state, narrow) {
+        return;
+    }
+
     // Layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .margin(2)
+        .margin(if narrow { 0 } else { 2 })
         .constraints(
             [
                 Constraint::Length(3),
@@ -33,47 +208,74 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, routes_table_state: &mut
         .split(f.size());
 
     let input_areas = Layout::default()
-        .direction(Direction::Horizontal)
-        .margin(0)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(chunks[0]);
-
-    let options_areas = Layout::default()
         .direction(Direction::Horizontal)
         .margin(0)
         .constraints(
             [
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-                Constraint::Percentage(10),
-                Constraint::Percentage(10),
-                Constraint::Percentage(10),
-                Constraint::Percentage(10),
-                Constraint::Percentage(10),
+                Constraint::Percentage(40),
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
             ]
             .as_ref(),
         )
-        .split(chunks[1]);
+        .split(chunks[0]);
 
-    let info_area = Layout::default()
+    let options_constraints = if narrow {
+        vec![
+            Constraint::Length(12),
+            Constraint::Length(7),
+            Constraint::Min(1),
+        ]
+    } else {
+        vec![
+            Constraint::Percentage(12),
+            Constraint::Percentage(12),
+            Constraint::Percentage(10),
+            Constraint::Min(1),
+        ]
+    };
+    let options_areas = Layout::default()
         .direction(Direction::Horizontal)
         .margin(0)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
-        .split(chunks[2]);
+        .constraints(options_constraints)
+        .split(chunks[1]);
 
-    let table_area = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
-        .split(info_area[0]);
+    // Wide: table and notifications on the left, details on the right.
+    // Narrow: table, details and notifications below each other.
+    let (table_area, details_area, notifications_area) = if narrow {
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(45),
+                Constraint::Percentage(35),
+                Constraint::Percentage(20),
+            ])
+            .split(chunks[2]);
+        (areas[0], areas[1], areas[2])
+    } else {
+        let info_area = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(0)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+            .split(chunks[2]);
+        let left_areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+            .split(info_area[0]);
+        (left_areas[0], info_area[1], left_areas[1])
+    };
 
     let start_area = input_areas[0];
-    let destination_area = input_areas[1];
+    let via_area = input_areas[1];
+    let destination_area = input_areas[2];
 
     //Input ares
     let input_start = start_paragraph(app);
+    let input_via = via_paragraph(app);
     let input_destination = desination_paragraph(app);
 
     f.render_widget(input_start, start_area);
+    f.render_widget(input_via, via_area);
     f.render_widget(input_destination, destination_area);
 
     // Option areas
@@ -83,32 +285,25 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, routes_table_state: &mut
     let time_panel = time_paragraph(app);
     f.render_widget(time_panel, options_areas[1]);
 
-    let arrival_panel = arrival_paragraph(app);
-    f.render_widget(arrival_panel, options_areas[2]);
-
-    let ubahn_panel = ubahn_paragraph(app);
-    f.render_widget(ubahn_panel, options_areas[3]);
-
-    let sbahn_panel = sbahn_paragraph(app);
-    f.render_widget(sbahn_panel, options_areas[4]);
-
-    let tram_panel = tram_paragraph(app);
-    f.render_widget(tram_panel, options_areas[5]);
+    if narrow {
+        let toggles_panel = toggles_paragraph(app);
+        f.render_widget(toggles_panel, options_areas[2]);
+    } else {
+        let arrival_panel = arrival_paragraph(app);
+        f.render_widget(arrival_panel, options_areas[2]);
 
-    let bus_panel = bus_paragraph(app);
-    f.render_widget(bus_panel, options_areas[6]);
-
-    // Routes pane
-    let routes = routes_table(app);
-    f.render_stateful_widget(routes, table_area[0], &mut routes_table_state.table_state);
-
-    // Routes details
-    let details = details_list(app, routes_table_state);
-    f.render_widget(details, info_area[1]);
+        let mode_areas = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, MODES.len() as u32); MODES.len()])
+            .split(options_areas[3]);
+        for (i, area) in mode_areas.into_iter().enumerate() {
+            f.render_widget(mode_paragraph(app, i), area);
+        }
+    }
 
-    // Notification area
-    let notification = notifications(app, routes_table_state);
-    f.render_widget(notification, table_area[1]);
+    render_routes(f, app, routes_table_state, table_area, narrow);
+    render_details(f, app, routes_table_state, details_area);
+    render_notifications(f, app, routes_table_state, notifications_area);
 
     // Help message
     let help_message = help_message(app);
@@ -116,51 +311,24 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, routes_table_state: &mut
     // let help_message = Paragraph::new(Text::from(app.datetime.to_string()));
     f.render_widget(help_message, chunks[3]);
 
-    // Fetching popup
-    if app.show_fetch_popup {
-        let popup_area = popup_rect(10, 5, f.size());
-        let block = Block::default()
-            .borders(Borders::ALL)
-            .title("Fetching")
-            .title_alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .add_modifier(Modifier::BOLD)
-                    .bg(Color::Blue)
-                    .fg(Color::Black),
-            );
-        f.render_widget(Clear, popup_area);
-        f.render_widget(block, popup_area);
-    }
-
-    // Datetime error popups
-    if app.wrong_date {
-        let date_popup_area = popup_rect(20, 20, f.size());
-        let wrong_date_paragraph =
-            wrong_datetime_paragraph("Please enter a valid date", "Date Error");
-        f.render_widget(Clear, date_popup_area);
-        f.render_widget(wrong_date_paragraph, date_popup_area);
-    }
-
-    if app.wrong_time {
-        let date_popup_area = popup_rect(20, 20, f.size());
-        let wrong_time_paragraph =
-            wrong_datetime_paragraph("Please enter a valid time", "Time Error");
-        f.render_widget(Clear, date_popup_area);
-        f.render_widget(wrong_time_paragraph, date_popup_area);
-    }
-
     // Cursor position
-    if let InputMode::Editing = app.input_mode {
+    if app.editing_filter {
+        let title_width = routes_title(app).width() as u16;
+        f.set_cursor(table_area.x + 1 + title_width, table_area.y);
+    } else if let InputMode::Editing = app.input_mode {
         match app.focus {
             Focus::Start => f.set_cursor(
                 input_areas[0].x + app.input_start.width() as u16 + 1,
                 input_areas[0].y + 1,
             ),
-            Focus::Destination => f.set_cursor(
-                input_areas[1].x + app.input_destination.width() as u16 + 1,
+            Focus::Via => f.set_cursor(
+                input_areas[1].x + app.input_via.width() as u16 + 1,
                 input_areas[1].y + 1,
             ),
+            Focus::Destination => f.set_cursor(
+                input_areas[2].x + app.input_destination.width() as u16 + 1,
+                input_areas[2].y + 1,
+            ),
             Focus::Date => f.set_cursor(
                 options_areas[0].x + app.input_date.width() as u16 + 1,
                 options_areas[0].y + 1,
@@ -173,3 +341,76 @@ pub fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, routes_table_state: &mut
         }
     }
 }
+
+/// Renders only the focused pane, with the help bar below it. Returns `false`
+/// if the focus is not on one of the panes that can be maximized.
+fn maximized_pane<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    routes_table_state: &mut RoutesTableState,
+    narrow: bool,
+) -> bool {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(if narrow { 0 } else { 1 })
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(f.size());
+    match app.focus {
+        Focus::Routes => render_routes(f, app, routes_table_state, chunks[0], narrow),
+        Focus::Details => render_details(f, app, routes_table_state, chunks[0]),
+        Focus::Notifications => render_notifications(f, app, routes_table_state, chunks[0]),
+        _ => return false,
+    }
+    f.render_widget(help_message(app), chunks[1]);
+    true
+}
+
+fn render_routes<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    routes_table_state: &mut RoutesTableState,
+    area: Rect,
+    narrow: bool,
+) {
+    let routes = routes_table(app, narrow);
+    f.render_stateful_widget(routes, area, &mut routes_table_state.table_state);
+}
+
+fn render_details<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    routes_table_state: &mut RoutesTableState,
+    area: Rect,
+) {
+    match app.details_view {
+        DetailsView::List => {
+            let details = details_list(app, routes_table_state);
+            f.render_stateful_widget(details, area, &mut routes_table_state.details_state);
+        }
+        DetailsView::Timeline => {
+            let details = timeline(app, routes_table_state, area.width);
+            f.render_widget(details, area);
+        }
+        DetailsView::Map => {
+            let details = route_map(app, routes_table_state);
+            f.render_widget(details, area);
+        }
+        DetailsView::Plan => f.render_widget(plan_list(app), area),
+    }
+}
+
+/// Renders the notifications, scrolling stops at the last line.
+fn render_notifications<B: Backend>(
+    f: &mut Frame<B>,
+    app: &App,
+    routes_table_state: &mut RoutesTableState,
+    area: Rect,
+) {
+    let visible = area.height.saturating_sub(2);
+    let height = notifications_height(app, routes_table_state, area.width.saturating_sub(2));
+    routes_table_state.notifications_scroll = routes_table_state
+        .notifications_scroll
+        .min(height.saturating_sub(visible));
+    let notification = notifications(app, routes_table_state);
+    f.render_widget(notification, area);
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, FixedOffset, Local};
+
+use crate::app::shown_time;
+use crate::networking::Departure;
+
+pub const DEPARTURES_HEADER: [&str; 6] = ["TIME", "IN", "LINE", "DESTINATION", "PLATFORM", "DELAY"];
+
+/// Text of the cells of one row of a departure board, in the order of `DEPARTURES_HEADER`.
+/// The `IN` column counts the minutes from `now` until the departure, the
+/// time is shown in `time_zone` (see `shown_time`).
+pub fn departure_cells(
+    dep: &Departure,
+    now: &DateTime<Local>,
+    time_zone: Option<FixedOffset>,
+) -> Vec<String> {
+    let time = shown_time(&dep.planned_departure_time, time_zone)
+        .format("%H:%M")
+        .to_string();
+    let in_minutes = if dep.cancelled {
+        "cancelled".to_string()
+    } else {
+        (dep.planned_departure_time - *now)
+            .num_minutes()
+            .to_string()
+    };
+    let platform = match dep.platform {
+        Some(p) => p.to_string(),
+        None => "-".to_string(),
+    };
+    let delay = match dep.delay_in_minutes {
+        Some(d) if d != 0 => d.to_string(),
+        _ => "-".to_string(),
+    };
+    vec![
+        time,
+        in_minutes,
+        dep.label.clone(),
+        dep.destination.clone(),
+        platform,
+        delay,
+    ]
+}
@@ -0,0 +1,93 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::App;
+use crate::disruptions::{by_line, plain_text, validity};
+use crate::keymap::{key_name, Action};
+
+/// Indentation of the texts below the line labels.
+const INDENT: &str = "    ";
+
+/// Splits `text` into rows of at most `width` columns at the spaces.
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        if !row.is_empty() && row.width() + 1 + word.width() > width {
+            rows.push(std::mem::take(&mut row));
+        }
+        if !row.is_empty() {
+            row.push(' ');
+        }
+        row.push_str(word);
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+    rows
+}
+
+/// Every disruption grouped by line with its validity and full text, word
+/// wrapped to `width`, so scrolling can stop at the last row.
+pub fn disruptions_text(app: &App, width: u16) -> Text<'static> {
+    let width = (width as usize).saturating_sub(INDENT.len()).max(1);
+    let line_style = Style::default()
+        .fg(app.theme.header)
+        .add_modifier(Modifier::BOLD);
+    let title_style = Style::default().add_modifier(Modifier::BOLD);
+    let muted = Style::default().fg(app.theme.muted);
+    let mut lines = Vec::new();
+    for (label, disruptions) in by_line(&app.disruptions) {
+        lines.push(Spans::from(Span::styled(label.to_string(), line_style)));
+        for disruption in disruptions {
+            for row in wrap_words(&disruption.title, width) {
+                lines.push(Spans::from(Span::styled(
+                    format!("{}{}", INDENT, row),
+                    title_style,
+                )));
+            }
+            if let Some(validity) = validity(disruption, app.time_zone) {
+                lines.push(Spans::from(Span::styled(
+                    format!("{}{}", INDENT, validity),
+                    muted,
+                )));
+            }
+            for paragraph in plain_text(&disruption.description).lines() {
+                for row in wrap_words(paragraph, width) {
+                    lines.push(Spans::from(format!("{}{}", INDENT, row)));
+                }
+            }
+            lines.push(Spans::default());
+        }
+    }
+    Text::from(lines)
+}
+
+pub fn disruptions_overview(app: &App, width: u16) -> Paragraph<'static> {
+    let close = app
+        .keymap
+        .help_bindings()
+        .iter()
+        .find(|b| b.action == Action::Leave)
+        .and_then(|b| b.keys.first())
+        .map(key_name)
+        .unwrap_or_default();
+    let text = match app.disruptions.is_empty() {
+        true => Text::styled(
+            "No disruptions reported",
+            Style::default().fg(app.theme.muted),
+        ),
+        false => disruptions_text(app, width),
+    };
+    Paragraph::new(text)
+        .scroll((app.disruptions_scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Disruptions ({} to close)", close)),
+        )
+}
@@ -1,53 +1,230 @@
 use tui::{
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::Paragraph,
+    widgets::{Block, Borders, Paragraph},
 };
 
 use crate::app::{App, InputMode};
+use crate::keymap::{key_name, Action, Binding, Keymap};
+
+type HelpEntry = (&'static [Action], &'static str);
+
+const NORMAL_HELP: &[HelpEntry] = &[
+    (&[Action::Quit], "Exit"),
+    (&[Action::ShowHelp], "Help"),
+    (&[Action::Activate], "Insert mode/toggle"),
+    (&[Action::Fetch], "Fetch data"),
+    (&[Action::ToggleArrival], "Dep/Arr"),
+    (&[Action::ToggleDetailsView], "Details/Timeline/Map"),
+    (&[Action::Maximize], "Maximize pane"),
+    (&[Action::ShowLog], "Messages"),
+    (&[Action::Filter], "Filter"),
+    (&[Action::ShowNearby], "Nearby stations"),
+    (&[Action::ShowDisruptions], "Disruptions"),
+    (
+        &[
+            Action::FocusLeft,
+            Action::FocusDown,
+            Action::FocusUp,
+            Action::FocusRight,
+        ],
+        "Navigation",
+    ),
+];
+
+const EDITING_HELP: &[HelpEntry] = &[(&[Action::Leave], "Normal Mode")];
+const FILTER_HELP: &[HelpEntry] = &[(&[Action::Leave], "Done")];
+
+const TABLE_HELP: &[HelpEntry] = &[
+    (&[Action::Leave], "Stop table navigation"),
+    (&[Action::ShowHelp], "Help"),
+    (&[Action::Sort, Action::ReverseSort], "Sort/reverse"),
+    (&[Action::Filter], "Filter"),
+    (&[Action::ToggleDetailsView], "Details/Timeline/Map"),
+    (&[Action::Maximize], "Maximize pane"),
+    (
+        &[
+            Action::ZoomIn,
+            Action::ZoomOut,
+            Action::PanLeft,
+            Action::PanDown,
+            Action::PanUp,
+            Action::PanRight,
+            Action::ResetMap,
+        ],
+        "Zoom/pan/reset map",
+    ),
+    (
+        &[Action::ExportTrip, Action::ExportLegs],
+        "Export trip/legs to .ics",
+    ),
+    (&[Action::Copy], "Copy to clipboard"),
+    (&[Action::ToggleStops], "Collapse stops"),
+    (&[Action::ShowDisruptions], "Disruptions"),
+    (&[Action::ShowLog], "Messages"),
+    (&[Action::NextEntry, Action::PreviousEntry], "Navigation"),
+];
+
+/// Keys of a help entry, e.g. `i / Enter` for a single action or `hjkl / ← ↓ ↑ →`
+/// for a group of actions.
+fn entry_keys(keymap: &Keymap, mode: InputMode, actions: &[Action]) -> String {
+    let keys = actions
+        .iter()
+        .map(|action| keymap.keys(mode, *action))
+        .filter(|keys| !keys.is_empty())
+        .collect::<Vec<_>>();
+    if let [keys] = keys.as_slice() {
+        return keys
+            .iter()
+            .map(key_name)
+            .collect::<Vec<String>>()
+            .join(" / ");
+    }
+    let primary = keys
+        .iter()
+        .map(|k| key_name(&k[0]))
+        .collect::<Vec<String>>();
+    // Runs of lowercase letters like `hjkl` read well without separators
+    let mut text = if primary
+        .iter()
+        .all(|k| k.chars().count() == 1 && k.chars().all(|c| c.is_ascii_lowercase()))
+    {
+        primary.join("")
+    } else {
+        primary.join(" ")
+    };
+    if keys.iter().all(|k| k.len() > 1) {
+        let secondary = keys
+            .iter()
+            .map(|k| key_name(&k[1]))
+            .collect::<Vec<String>>();
+        text.push_str(&format!(" / {}", secondary.join(" ")));
+    }
+    text
+}
 
 pub fn help_message(app: &App) -> Paragraph {
-    let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
-            vec![
-                Span::styled("q: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw("Exit, "),
-                Span::styled("i / Enter: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw("Insert mode/toggle, "),
-                Span::styled("f / Space: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw("Fetch data, "),
-                Span::styled(
-                    "hjkl / ← ↑ ↓ →: ",
-                    Style::default().add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("Navigation "),
-            ],
-            Style::default().fg(Color::Cyan),
-        ),
-        InputMode::Editing => (
-            vec![
-                Span::styled(
-                    "Esc / Enter: ",
-                    Style::default().add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("Normal Mode "),
-            ],
-            Style::default().fg(Color::Cyan),
-        ),
-        InputMode::Table => (
-            vec![
-                Span::styled(
-                    "Esc / Enter: ",
-                    Style::default().add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("Stop table navigation, "),
-                Span::styled("jk / ↑ ↓: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw("Navigation "),
-            ],
-            Style::default().fg(Color::Cyan),
-        ),
+    let entries = match app.input_mode {
+        InputMode::Normal => NORMAL_HELP,
+        InputMode::Editing if app.editing_filter => FILTER_HELP,
+        InputMode::Editing => EDITING_HELP,
+        InputMode::Table => TABLE_HELP,
     };
+    let mut msg = Vec::new();
+    for (i, (actions, description)) in entries.iter().enumerate() {
+        let keys = entry_keys(&app.keymap, app.input_mode, actions);
+        if keys.is_empty() {
+            continue;
+        }
+        let separator = if i == entries.len() - 1 { " " } else { ", " };
+        msg.push(Span::styled(
+            format!("{}: ", keys),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        msg.push(Span::raw(format!("{}{}", description, separator)));
+    }
+    let style = Style::default().fg(app.theme.help);
     let mut text = Text::from(Spans::from(msg));
     text.patch_style(style);
     Paragraph::new(text)
 }
+
+/// Explanations of the routes table columns, shown in the help overlay.
+const COLUMNS_HELP: &[(&str, &str)] = &[
+    (
+        "TIME",
+        "Planned departure at the start and arrival at the destination",
+    ),
+    ("IN", "Minutes until departure"),
+    ("DURATION", "Travel time in minutes"),
+    ("LINES", "Lines of the connection, walk for footpaths"),
+    (
+        "DELAY",
+        "Departure delay at the start in minutes, - if on time",
+    ),
+    (
+        "INFO",
+        "Notifications of the lines, in full in the Notifications pane",
+    ),
+];
+
+fn binding_spans(bindings: &[Binding], title: &str, style: Style) -> Vec<Spans<'static>> {
+    let mut lines = vec![Spans::from(Span::styled(title.to_string(), style))];
+    for binding in bindings.iter().filter(|b| !b.keys.is_empty()) {
+        let keys = binding
+            .keys
+            .iter()
+            .map(key_name)
+            .collect::<Vec<String>>()
+            .join(" / ");
+        lines.push(Spans::from(vec![
+            Span::styled(
+                format!("  {:<16}", keys),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(binding.action.description().to_string()),
+        ]));
+    }
+    lines.push(Spans::default());
+    lines
+}
+
+/// Every key binding grouped by input mode, followed by the column
+/// explanations of the routes table.
+pub fn help_overlay_text(app: &App) -> Text<'static> {
+    let title = Style::default()
+        .fg(app.theme.header)
+        .add_modifier(Modifier::BOLD);
+    let keymap = &app.keymap;
+    let mut lines = Vec::new();
+    lines.extend(binding_spans(
+        keymap.bindings(InputMode::Normal),
+        "Navigation",
+        title,
+    ));
+    lines.extend(binding_spans(
+        keymap.bindings(InputMode::Editing),
+        "Editing",
+        title,
+    ));
+    lines.extend(binding_spans(
+        keymap.bindings(InputMode::Table),
+        "Routes table",
+        title,
+    ));
+    lines.extend(binding_spans(keymap.help_bindings(), "Help", title));
+    lines.extend(binding_spans(
+        keymap.nearby_bindings(),
+        "Nearby stations",
+        title,
+    ));
+    lines.push(Spans::from(Span::styled("Routes table columns", title)));
+    for (column, description) in COLUMNS_HELP {
+        lines.push(Spans::from(vec![
+            Span::styled(
+                format!("  {:<16}", column),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(*description),
+        ]));
+    }
+    Text::from(lines)
+}
+
+pub fn help_overlay(app: &App) -> Paragraph {
+    let close = app
+        .keymap
+        .help_bindings()
+        .iter()
+        .find(|b| b.action == Action::Leave)
+        .and_then(|b| b.keys.first())
+        .map(key_name)
+        .unwrap_or_default();
+    Paragraph::new(help_overlay_text(app))
+        .scroll((app.help_scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Help ({} to close)", close)),
+        )
+}
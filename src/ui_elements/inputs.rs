@@ -1,16 +1,18 @@
 use tui::{
-    style::{Color, Style},
+    style::{Modifier, Style},
+    text::{Span, Spans},
     widgets::{Block, Borders, Paragraph},
 };
 
 use crate::app::{App, Focus, InputMode};
+use crate::modes::MODES;
 
 pub fn start_paragraph(app: &App) -> Paragraph {
     Paragraph::new(app.input_start.as_ref())
         .style(match app.input_mode {
             InputMode::Normal => {
                 if let Focus::Start = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
@@ -18,14 +20,14 @@ pub fn start_paragraph(app: &App) -> Paragraph {
 
             InputMode::Editing => {
                 if let Focus::Start = app.focus {
-                    Style::default().fg(Color::Yellow)
+                    Style::default().fg(app.theme.editing)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Table => {
                 if let Focus::Start = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
@@ -34,26 +36,54 @@ pub fn start_paragraph(app: &App) -> Paragraph {
         .block(Block::default().borders(Borders::ALL).title("Start"))
 }
 
+pub fn via_paragraph(app: &App) -> Paragraph {
+    Paragraph::new(app.input_via.as_ref())
+        .style(match app.input_mode {
+            InputMode::Normal => {
+                if let Focus::Via = app.focus {
+                    Style::default().fg(app.theme.focus)
+                } else {
+                    Style::default()
+                }
+            }
+            InputMode::Editing => {
+                if let Focus::Via = app.focus {
+                    Style::default().fg(app.theme.editing)
+                } else {
+                    Style::default()
+                }
+            }
+            InputMode::Table => {
+                if let Focus::Via = app.focus {
+                    Style::default().fg(app.theme.focus)
+                } else {
+                    Style::default()
+                }
+            }
+        })
+        .block(Block::default().borders(Borders::ALL).title("Via"))
+}
+
 pub fn desination_paragraph(app: &App) -> Paragraph {
     Paragraph::new(app.input_destination.as_ref())
         .style(match app.input_mode {
             InputMode::Normal => {
                 if let Focus::Destination = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Editing => {
                 if let Focus::Destination = app.focus {
-                    Style::default().fg(Color::Yellow)
+                    Style::default().fg(app.theme.editing)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Table => {
                 if let Focus::Destination = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
@@ -67,21 +97,21 @@ pub fn date_paragraph(app: &App) -> Paragraph {
         .style(match app.input_mode {
             InputMode::Normal => {
                 if let Focus::Date = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Editing => {
                 if let Focus::Date = app.focus {
-                    Style::default().fg(Color::Yellow)
+                    Style::default().fg(app.theme.editing)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Table => {
                 if let Focus::Date = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
@@ -95,21 +125,21 @@ pub fn time_paragraph(app: &App) -> Paragraph {
         .style(match app.input_mode {
             InputMode::Normal => {
                 if let Focus::Time = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Editing => {
                 if let Focus::Time = app.focus {
-                    Style::default().fg(Color::Yellow)
+                    Style::default().fg(app.theme.editing)
                 } else {
                     Style::default()
                 }
             }
             InputMode::Table => {
                 if let Focus::Time = app.focus {
-                    Style::default().fg(Color::Blue)
+                    Style::default().fg(app.theme.focus)
                 } else {
                     Style::default()
                 }
@@ -125,68 +155,70 @@ pub fn arrival_paragraph(app: &App) -> Paragraph {
     };
     Paragraph::new(text)
         .style(match app.focus {
-            Focus::Arrival => Style::default().fg(Color::Blue),
+            Focus::Arrival => Style::default().fg(app.theme.focus),
             _ => Style::default(),
         })
         .block(Block::default().borders(Borders::ALL).title("Dep <-> Arr"))
 }
 
-pub fn ubahn_paragraph(app: &App) -> Paragraph {
-    let (text, fg) = match app.use_ubahn {
-        true => ("True", Color::Green),
-        false => ("False", Color::Red),
+pub fn mode_paragraph(app: &App, idx: usize) -> Paragraph {
+    let (text, fg) = match app.modes[idx] {
+        true => ("True", app.theme.toggle_on),
+        false => ("False", app.theme.toggle_off),
     };
     let style = match app.focus {
-        Focus::Ubahn => Style::default().fg(Color::Blue),
+        Focus::Mode(i) if i == idx => Style::default().fg(app.theme.focus),
         _ => Style::default().fg(fg),
     };
 
-    Paragraph::new(text)
-        .style(style)
-        .block(Block::default().borders(Borders::ALL).title("U-Bahn"))
+    Paragraph::new(text).style(style).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(MODES[idx].name),
+    )
 }
 
-pub fn sbahn_paragraph(app: &App) -> Paragraph {
-    let (text, fg) = match app.use_sbahn {
-        true => ("True", Color::Green),
-        false => ("False", Color::Red),
+/// Arrival and the transport modes in a single box for narrow terminals,
+/// e.g. `Dep U S T B R RB RT`. Enabled modes use the toggle colours, the focused
+/// option is shown reversed.
+pub fn toggles_paragraph(app: &App) -> Paragraph {
+    let toggle = |label: &'static str, on: bool, focus: Focus| {
+        let fg = match on {
+            true => app.theme.toggle_on,
+            false => app.theme.toggle_off,
+        };
+        let style = match app.focus == focus {
+            true => Style::default()
+                .fg(app.theme.focus)
+                .add_modifier(Modifier::REVERSED),
+            false => Style::default().fg(fg),
+        };
+        Span::styled(label, style)
     };
-    let style = match app.focus {
-        Focus::Sbahn => Style::default().fg(Color::Blue),
-        _ => Style::default().fg(fg),
+    let arrival = match app.is_arrival {
+        true => "Arr",
+        false => "Dep",
     };
-
-    Paragraph::new(text)
-        .style(style)
-        .block(Block::default().borders(Borders::ALL).title("S-Bahn"))
-}
-
-pub fn tram_paragraph(app: &App) -> Paragraph {
-    let (text, fg) = match app.use_tram {
-        true => ("True", Color::Green),
-        false => ("False", Color::Red),
+    let arrival_style = match app.focus {
+        Focus::Arrival => Style::default()
+            .fg(app.theme.focus)
+            .add_modifier(Modifier::REVERSED),
+        _ => Style::default(),
     };
-    let style = match app.focus {
-        Focus::Tram => Style::default().fg(Color::Blue),
-        _ => Style::default().fg(fg),
+    let mut spans = vec![Span::styled(arrival, arrival_style)];
+    for (i, mode) in MODES.iter().enumerate() {
+        spans.push(Span::raw(" "));
+        spans.push(toggle(mode.short, app.modes[i], Focus::Mode(i)));
+    }
+    let text = Spans::from(spans);
+    let border_style = match app.focus {
+        Focus::Arrival | Focus::Mode(_) => Style::default().fg(app.theme.focus),
+        _ => Style::default(),
     };
-
-    Paragraph::new(text)
-        .style(style)
-        .block(Block::default().borders(Borders::ALL).title("Tram"))
-}
-
-pub fn bus_paragraph(app: &App) -> Paragraph {
-    let (text, fg) = match app.use_bus {
-        true => ("True", Color::Green),
-        false => ("False", Color::Red),
-    };
-    let style = match app.focus {
-        Focus::Bus => Style::default().fg(Color::Blue),
-        _ => Style::default().fg(fg),
-    };
-
-    Paragraph::new(text)
-        .style(style)
-        .block(Block::default().borders(Borders::ALL).title("Bus"))
+    Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title("Options"),
+    )
 }
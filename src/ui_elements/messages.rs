@@ -0,0 +1,97 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{shown_time, App};
+use crate::keymap::{key_name, Action};
+use crate::messages::{Message, Severity};
+use crate::theme::Theme;
+
+fn severity_style(severity: Severity, theme: &Theme) -> Style {
+    let color = match severity {
+        Severity::Info => theme.info,
+        Severity::Warning => theme.warning,
+        Severity::Error => theme.error,
+    };
+    Style::default().fg(color)
+}
+
+fn message_spans<'a>(msg: &'a Message, theme: &Theme) -> Spans<'a> {
+    Spans::from(vec![
+        Span::styled(
+            format!("{:<6}", msg.severity.label()),
+            severity_style(msg.severity, theme).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(msg.text.as_str()),
+    ])
+}
+
+/// Every message of the session with its time, oldest first.
+pub fn message_log_text(app: &App) -> Text {
+    let lines = app
+        .messages
+        .iter()
+        .map(|msg| {
+            let mut spans = message_spans(msg, &app.theme);
+            spans.0.insert(
+                0,
+                Span::styled(
+                    format!(
+                        "{}  ",
+                        shown_time(&msg.time, app.time_zone).format("%H:%M:%S")
+                    ),
+                    Style::default().fg(app.theme.muted),
+                ),
+            );
+            spans
+        })
+        .collect::<Vec<Spans>>();
+    Text::from(lines)
+}
+
+pub fn message_log(app: &App) -> Paragraph {
+    let close = app
+        .keymap
+        .help_bindings()
+        .iter()
+        .find(|b| b.action == Action::Leave)
+        .and_then(|b| b.keys.first())
+        .map(key_name)
+        .unwrap_or_default();
+    let text = match app.messages.is_empty() {
+        true => Text::styled("No messages yet", Style::default().fg(app.theme.muted)),
+        false => message_log_text(app),
+    };
+    Paragraph::new(text).scroll((app.log_scroll, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Messages ({} to close)", close)),
+    )
+}
+
+/// Width and height of the toasts box for `toasts`, at most `max_width` wide.
+pub fn toasts_size(app: &App, max_width: u16) -> (u16, u16) {
+    let toasts = app.toasts();
+    let width = toasts
+        .iter()
+        .map(|msg| 6 + msg.text.width() as u16 + 2)
+        .max()
+        .unwrap_or(0)
+        .min(max_width);
+    (width, toasts.len() as u16 + 2)
+}
+
+/// The latest messages as popup, they disappear after a few seconds.
+pub fn toasts(app: &App) -> Paragraph {
+    let lines = app
+        .toasts()
+        .into_iter()
+        .map(|msg| message_spans(msg, &app.theme))
+        .collect::<Vec<Spans>>();
+    Paragraph::new(Text::from(lines))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL))
+}
@@ -0,0 +1,71 @@
+use tui::{
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::Paragraph,
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::{App, Focus, InputMode, RoutesTableState};
+
+use super::{help_message, route_cells};
+
+/// Label and content of the field being edited.
+fn editing_field(app: &App) -> Option<(&'static str, &str)> {
+    if app.input_mode != InputMode::Editing {
+        return None;
+    }
+    if app.editing_filter {
+        return Some(("Filter", &app.filter));
+    }
+    match app.focus {
+        Focus::Start => Some(("Start", &app.input_start)),
+        Focus::Via => Some(("Via", &app.input_via)),
+        Focus::Destination => Some(("Destination", &app.input_destination)),
+        Focus::Date => Some(("Date", &app.input_date)),
+        Focus::Time => Some(("Time", &app.input_time)),
+        _ => None,
+    }
+}
+
+/// The whole UI in one line for very small panes: the field being edited, the
+/// selected (or next) connection, or the help bar if nothing was fetched yet.
+pub fn minimal_line<'a>(app: &'a App, routes_table_state: &RoutesTableState) -> Paragraph<'a> {
+    if let Some((label, input)) = editing_field(app) {
+        return Paragraph::new(Spans::from(vec![
+            Span::styled(
+                format!("{}: ", label),
+                Style::default().fg(app.theme.editing),
+            ),
+            Span::raw(input),
+        ]));
+    }
+    let idx = routes_table_state.table_state.selected().unwrap_or(0);
+    let conn = match app.routes.get(idx) {
+        Some(conn) => conn,
+        None => return help_message(app),
+    };
+    let cells = route_cells(conn, &app.now, app.time_zone);
+    let mut spans = vec![
+        Span::styled(
+            format!("{}/{} ", idx + 1, app.routes.len()),
+            Style::default().fg(app.theme.muted),
+        ),
+        Span::styled(
+            cells[0].clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" in {} min  {}", cells[1], cells[3])),
+    ];
+    if cells[4] != "-" {
+        spans.push(Span::styled(
+            format!("  +{}", cells[4]),
+            Style::default().fg(app.theme.error),
+        ));
+    }
+    Paragraph::new(Spans::from(spans))
+}
+
+/// Cursor column in the minimal line while editing.
+pub fn minimal_cursor(app: &App) -> Option<u16> {
+    editing_field(app).map(|(label, input)| (label.width() + 2 + input.width()) as u16)
+}
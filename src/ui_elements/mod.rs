@@ -1,23 +1,59 @@
+mod departures;
+mod disruptions;
 mod help_message;
 mod inputs;
+mod messages;
+mod minimal;
+mod nearby;
 mod popups;
+mod route_map;
 mod routes_details;
+mod timeline;
 
 pub use inputs::arrival_paragraph;
-pub use inputs::bus_paragraph;
 pub use inputs::date_paragraph;
 pub use inputs::desination_paragraph;
-pub use inputs::sbahn_paragraph;
+pub use inputs::mode_paragraph;
 pub use inputs::start_paragraph;
 pub use inputs::time_paragraph;
-pub use inputs::tram_paragraph;
-pub use inputs::ubahn_paragraph;
+pub use inputs::toggles_paragraph;
+pub use inputs::via_paragraph;
+
+pub use messages::message_log;
+pub use messages::message_log_text;
+pub use messages::toasts;
+pub use messages::toasts_size;
+
+pub use minimal::minimal_cursor;
+pub use minimal::minimal_line;
+
+pub use nearby::nearby_cells;
+pub use nearby::nearby_table;
+pub use nearby::NEARBY_HEADER;
 
 pub use popups::popup_rect;
 pub use popups::wrong_datetime_paragraph;
 
+pub use route_map::route_map;
+
 pub use routes_details::details_list;
 pub use routes_details::notifications;
+pub use routes_details::notifications_height;
+pub use routes_details::plan_list;
+pub use routes_details::route_cells;
 pub use routes_details::routes_table;
+pub use routes_details::routes_title;
+pub use routes_details::ROUTES_HEADER;
+
+pub use disruptions::disruptions_overview;
+pub use disruptions::disruptions_text;
+
+pub use departures::departure_cells;
+pub use departures::DEPARTURES_HEADER;
 
 pub use help_message::help_message;
+pub use help_message::help_overlay;
+pub use help_message::help_overlay_text;
+
+pub use timeline::line_color;
+pub use timeline::timeline;
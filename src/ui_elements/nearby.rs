@@ -0,0 +1,98 @@
+use tui::{
+    layout::Constraint,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table},
+};
+
+use crate::app::App;
+use crate::keymap::{key_name, Action};
+use crate::networking::NearbyStation;
+use crate::routes_view::WalkingSpeed;
+use crate::ui_elements::{departure_cells, DEPARTURES_HEADER};
+
+pub const NEARBY_HEADER: [&str; 4] = ["STATION", "DISTANCE", "WALK", "LINES"];
+
+const NEARBY_WIDTHS: [Constraint; 4] = [
+    Constraint::Percentage(35),
+    Constraint::Length(10),
+    Constraint::Length(8),
+    Constraint::Min(10),
+];
+
+const DEPARTURES_WIDTHS: [Constraint; 6] = [
+    Constraint::Length(6),
+    Constraint::Length(10),
+    Constraint::Length(6),
+    Constraint::Min(20),
+    Constraint::Length(10),
+    Constraint::Length(6),
+];
+
+/// Text of the cells of one nearby station, in the order of `NEARBY_HEADER`.
+/// The walking time is the distance as the crow flies at `speed`.
+pub fn nearby_cells(station: &NearbyStation, speed: WalkingSpeed) -> Vec<String> {
+    vec![
+        station.name.clone(),
+        format!("{} m", station.distance_in_meters),
+        format!("{} min", speed.walking_minutes(station.distance_in_meters)),
+        station.lines().join(" "),
+    ]
+}
+
+fn header_row<'a>(app: &App, header: &[&'a str]) -> Row<'a> {
+    let cells = header
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().fg(app.theme.header)));
+    Row::new(cells).height(1).bottom_margin(1)
+}
+
+fn close_key(app: &App) -> String {
+    app.keymap
+        .nearby_bindings()
+        .iter()
+        .find(|b| b.action == Action::Leave)
+        .and_then(|b| b.keys.first())
+        .map(key_name)
+        .unwrap_or_default()
+}
+
+/// The nearby stations, or the departure board of the selected one if
+/// `app.nearby_board` is set. Render it with a `TableState` selecting
+/// `app.nearby_selected`.
+pub fn nearby_table(app: &App) -> Table {
+    let station = app.nearby.get(app.nearby_selected);
+    let (title, header, rows, widths) = match station {
+        Some(station) if app.nearby_board => (
+            format!(
+                "Departures at {} ({} to go back)",
+                station.name,
+                close_key(app)
+            ),
+            header_row(app, &DEPARTURES_HEADER),
+            station
+                .departures
+                .iter()
+                .map(|dep| Row::new(departure_cells(dep, &app.now, app.time_zone)))
+                .collect::<Vec<Row>>(),
+            &DEPARTURES_WIDTHS[..],
+        ),
+        _ => (
+            format!(
+                "Stations near {} ({} to close)",
+                app.nearby_location,
+                close_key(app)
+            ),
+            header_row(app, &NEARBY_HEADER),
+            app.nearby
+                .iter()
+                .map(|station| Row::new(nearby_cells(station, app.preferences.walking_speed)))
+                .collect::<Vec<Row>>(),
+            &NEARBY_WIDTHS[..],
+        ),
+    };
+    Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(widths)
+}
@@ -1,13 +1,19 @@
 use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     widgets::{Block, Borders, Paragraph},
 };
 
-pub fn wrong_datetime_paragraph<'a>(msg: &'a str, title: &'a str) -> Paragraph<'a> {
+use crate::theme::Theme;
+
+pub fn wrong_datetime_paragraph<'a>(msg: &'a str, title: &'a str, theme: &Theme) -> Paragraph<'a> {
     Paragraph::new(msg)
         .alignment(Alignment::Center)
-        .style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red))
+        .style(
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(theme.error),
+        )
         .block(
             Block::default()
                 .borders(Borders::ALL)
@@ -0,0 +1,130 @@
+use mvg_api::routes::Station;
+use tui::{
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::{
+        canvas::{Canvas, Context, Line, Points},
+        Block, Borders,
+    },
+};
+
+use crate::app::{App, RoutesTableState};
+
+use super::line_color;
+
+struct Leg {
+    coords: Vec<(f64, f64)>,
+    color: Color,
+    from: String,
+    to: String,
+}
+
+fn coords(station: &Station) -> (f64, f64) {
+    (station.longitude, station.latitude)
+}
+
+fn prepare_legs(app: &App, routes_table_state: &RoutesTableState) -> Vec<Leg> {
+    let mut legs = Vec::new();
+    if let Some(conn) = routes_table_state.selected_route(app) {
+        for cp in &conn.parts {
+            let mut leg_coords = vec![coords(&cp.from)];
+            for stop in &cp.intermediate_stops {
+                leg_coords.push(coords(stop));
+            }
+            leg_coords.push(coords(&cp.to));
+            legs.push(Leg {
+                coords: leg_coords,
+                color: line_color(&cp.line.label),
+                from: cp.from.name.clone(),
+                to: cp.to.name.clone(),
+            });
+        }
+    }
+    legs
+}
+
+fn bounds(legs: &[Leg], zoom: f64, pan: (f64, f64)) -> ([f64; 2], [f64; 2]) {
+    let all = legs.iter().flat_map(|leg| leg.coords.iter());
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for (x, y) in all {
+        min_x = min_x.min(*x);
+        max_x = max_x.max(*x);
+        min_y = min_y.min(*y);
+        max_y = max_y.max(*y);
+    }
+    if min_x > max_x {
+        return ([0.0, 0.0], [0.0, 0.0]);
+    }
+    // Add some padding, so stations at the edges are not cut off
+    let span_x = (max_x - min_x).max(0.005) * 1.2;
+    let span_y = (max_y - min_y).max(0.005) * 1.2;
+    let center_x = (min_x + max_x) / 2.0 + pan.0 * span_x;
+    let center_y = (min_y + max_y) / 2.0 + pan.1 * span_y;
+    let half_x = span_x / 2.0 / zoom;
+    let half_y = span_y / 2.0 / zoom;
+    (
+        [center_x - half_x, center_x + half_x],
+        [center_y - half_y, center_y + half_y],
+    )
+}
+
+fn draw_legs(ctx: &mut Context, legs: &[Leg], stop_color: Color) {
+    for leg in legs {
+        for pair in leg.coords.windows(2) {
+            ctx.draw(&Line {
+                x1: pair[0].0,
+                y1: pair[0].1,
+                x2: pair[1].0,
+                y2: pair[1].1,
+                color: leg.color,
+            });
+        }
+    }
+    ctx.layer();
+    for leg in legs {
+        let stops = &leg.coords[1..leg.coords.len() - 1];
+        ctx.draw(&Points {
+            coords: stops,
+            color: stop_color,
+        });
+    }
+    for (i, leg) in legs.iter().enumerate() {
+        let (x, y) = leg.coords[0];
+        ctx.print(
+            x,
+            y,
+            Span::styled(
+                format!("● {}", leg.from),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        );
+        if i == legs.len() - 1 {
+            let (x, y) = leg.coords[leg.coords.len() - 1];
+            ctx.print(
+                x,
+                y,
+                Span::styled(
+                    format!("● {}", leg.to),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            );
+        }
+    }
+}
+
+pub fn route_map<'a>(
+    app: &'a App,
+    routes_table_state: &RoutesTableState,
+) -> Canvas<'a, impl Fn(&mut Context) + 'a> {
+    let legs = prepare_legs(app, routes_table_state);
+    let (x_bounds, y_bounds) = bounds(&legs, app.map_zoom, app.map_pan);
+    let stop_color = app.theme.map_stop;
+
+    Canvas::default()
+        .block(Block::default().borders(Borders::ALL).title("Map"))
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds(y_bounds)
+        .paint(move |ctx| draw_legs(ctx, &legs, stop_color))
+}
@@ -1,19 +1,80 @@
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, Local};
 use itertools::Itertools;
 use mvg_api::routes::{Connection, ConnectionPart, Station};
 use tui::{
     layout::Constraint,
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Span, Text},
-    widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
 };
 
-use crate::app::{App, Focus, InputMode, RoutesTableState};
+use unicode_width::UnicodeWidthStr;
 
-pub fn routes_table(app: &App) -> Table {
-    let header_cells = ["TIME", "IN", "DURATION", "LINES", "DELAY", "INFO"]
-        .iter()
-        .map(|h| Cell::from(*h).style(Style::default().fg(Color::Magenta)));
+use crate::app::{shown_time, App, Focus, InputMode, RoutesTableState};
+use crate::disruptions::affected_lines;
+use crate::itinerary::{arrival, departure};
+use crate::routes_view::{distance, is_walk, SortKey};
+
+pub const ROUTES_HEADER: [&str; 6] = ["TIME", "IN", "DURATION", "LINES", "DELAY", "INFO"];
+
+/// Indices into `ROUTES_HEADER` with the column widths, all of them or only
+/// the important ones for narrow terminals.
+const ROUTES_COLUMNS: (&[usize], &[Constraint]) = (
+    &[0, 1, 2, 3, 4, 5],
+    &[
+        Constraint::Percentage(20),
+        Constraint::Percentage(10),
+        Constraint::Percentage(14),
+        Constraint::Percentage(20),
+        Constraint::Percentage(10),
+        Constraint::Percentage(32),
+    ],
+);
+const COMPACT_ROUTES_COLUMNS: (&[usize], &[Constraint]) = (
+    &[0, 1, 3, 4],
+    &[
+        Constraint::Percentage(35),
+        Constraint::Percentage(15),
+        Constraint::Percentage(35),
+        Constraint::Percentage(15),
+    ],
+);
+
+/// Title of the routes table with the sort key, unless it is the departure,
+/// and the filter. The filter comes last, so the cursor can be put behind it.
+pub fn routes_title(app: &App) -> String {
+    let mut title = "Routes".to_string();
+    if app.sort_key != SortKey::Departure {
+        title.push_str(&format!(" - by {}", app.sort_key.name()));
+    }
+    if !app.filtered_routes.is_empty() {
+        title.push_str(&format!(" - {} hidden", app.filtered_routes.len()));
+    }
+    if app.editing_filter || !app.filter.is_empty() {
+        title.push_str(&format!(" - /{}", app.filter));
+    }
+    title
+}
+
+/// The routes table; `compact` drops the duration and info columns, the info
+/// is still shown in the notifications pane.
+pub fn routes_table(app: &App, compact: bool) -> Table {
+    let (columns, widths) = if compact {
+        COMPACT_ROUTES_COLUMNS
+    } else {
+        ROUTES_COLUMNS
+    };
+    let arrow = match app.sort_descending {
+        true => "▼",
+        false => "▲",
+    };
+    let header_cells = columns.iter().map(|i| {
+        let text = match app.sort_key.column() == Some(*i) {
+            true => format!("{} {}", ROUTES_HEADER[*i], arrow),
+            false => ROUTES_HEADER[*i].to_string(),
+        };
+        Cell::from(text).style(Style::default().fg(app.theme.header))
+    });
     let header = Row::new(header_cells)
         .style(Style::default())
         .height(1)
@@ -21,44 +82,56 @@ pub fn routes_table(app: &App) -> Table {
 
     let items = &app.routes;
 
-    let rows = items.iter().map(prepare_routes);
+    let rows = items.iter().map(|conn| prepare_routes(conn, app, columns));
 
     Table::new(rows)
         .header(header)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Routes")
-                .border_style(match app.focus {
-                    Focus::Routes => match app.input_mode {
-                        InputMode::Table => Style::default().fg(Color::Yellow),
-                        _ => Style::default().fg(Color::Blue),
-                    },
-                    _ => Style::default(),
-                }),
+                .title(routes_title(app))
+                .border_style(pane_border(app, Focus::Routes)),
         )
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
         // .highlight_symbol("> ")
-        .widths(&[
-            Constraint::Percentage(20),
-            Constraint::Percentage(10),
-            Constraint::Percentage(14),
-            Constraint::Percentage(20),
-            Constraint::Percentage(10),
-            Constraint::Percentage(32),
-        ])
+        .widths(widths)
 }
 
-fn prepare_routes(conn: &Connection) -> Row {
+/// A row of the routes table, the lines marked with `⚠` if one of them has a
+/// disruption, see `disruptions_overview`.
+fn prepare_routes<'a>(conn: &Connection, app: &App, columns: &[usize]) -> Row<'a> {
     let height = 1;
+    let mut cells = route_cells(conn, &app.now, app.time_zone);
+    let disrupted = !affected_lines(conn, &app.disruptions).is_empty();
+    if disrupted {
+        cells[3] = format!("⚠ {}", cells[3]);
+    }
+    Row::new(columns.iter().map(|i| match (*i, disrupted) {
+        (3, true) => Cell::from(cells[3].clone()).style(Style::default().fg(app.theme.warning)),
+        _ => Cell::from(cells[*i].clone()),
+    }))
+    .height(height as u16)
+    .bottom_margin(0)
+    .style(Style::default())
+}
+
+/// Text of the cells of one row of the routes table, in the order of `ROUTES_HEADER`.
+/// The `IN` column counts the minutes from `now` until the departure, the
+/// times are shown in `time_zone` (see `shown_time`).
+pub fn route_cells(
+    conn: &Connection,
+    now: &DateTime<Local>,
+    time_zone: Option<FixedOffset>,
+) -> Vec<String> {
     let origin = &conn.parts[0].from;
     let destination = &conn.parts[conn.parts.len() - 1].to;
+    let departure = shown_time(&origin.planned_departure, time_zone);
     let time = format!(
         "{} - {}",
-        origin.planned_departure.format("%H:%M"),
-        destination.planned_departure.format("%H:%M")
+        departure.format("%H:%M"),
+        shown_time(&destination.planned_departure, time_zone).format("%H:%M")
     );
-    let in_minutes = (origin.planned_departure.time() - Local::now().time())
+    let in_minutes = (departure.time() - shown_time(now, time_zone).time())
         .num_minutes()
         .to_string();
     let duration = (destination.planned_departure.time() - origin.planned_departure.time())
@@ -67,17 +140,13 @@ fn prepare_routes(conn: &Connection) -> Row {
     let lines = prepare_lines(&conn.parts);
     let delay = prepare_delay(origin);
     let info = prepare_info(&conn.parts);
-    let cells = vec![time, in_minutes, duration, lines, delay, info];
-    Row::new(cells)
-        .height(height as u16)
-        .bottom_margin(0)
-        .style(Style::default())
+    vec![time, in_minutes, duration, lines, delay, info]
 }
 
 fn prepare_lines(cp_list: &[ConnectionPart]) -> String {
     let mut lines = Vec::new();
     for cp in cp_list.iter() {
-        if cp.line.label == "FOOTWAY" {
+        if is_walk(cp) {
             lines.push("walk");
         } else {
             lines.push(&cp.line.label);
@@ -86,7 +155,7 @@ fn prepare_lines(cp_list: &[ConnectionPart]) -> String {
     lines.iter().unique().join(", ")
 }
 
-fn prepare_delay(origin_station: &Station) -> String {
+pub fn prepare_delay(origin_station: &Station) -> String {
     match origin_station.departure_delay_in_minutes {
         Some(d) if d != 0 => d.to_string(),
         _ => "-".to_string(),
@@ -103,48 +172,115 @@ fn prepare_info(cp_list: &[ConnectionPart]) -> String {
     info
 }
 
-pub fn notifications<'a>(app: &'a App, routes_table_state: &RoutesTableState) -> Paragraph<'a> {
-    let mut nots = Vec::new();
-    // let mut not = "".to_string();
-    for i in &app.routes {
-        let not = prepare_info(&i.parts);
-        nots.push(not);
+/// Border of a focusable pane, highlighted while focused and while navigating
+/// or filtering it.
+fn pane_border(app: &App, pane: Focus) -> Style {
+    if app.focus != pane {
+        return Style::default();
     }
-    let curr_not = if let Some(selected) = routes_table_state.table_state.selected() {
-        &nots[selected]
-    } else {
-        ""
+    match app.input_mode {
+        InputMode::Table | InputMode::Editing => Style::default().fg(app.theme.editing),
+        InputMode::Normal => Style::default().fg(app.theme.focus),
+    }
+}
+
+/// One line per message of the selected connection, prefixed with its line.
+fn notification_lines(app: &App, routes_table_state: &RoutesTableState) -> Vec<String> {
+    let conn = match routes_table_state.selected_route(app) {
+        Some(conn) => conn,
+        None => return Vec::new(),
     };
+    conn.parts
+        .iter()
+        .flat_map(|cp| {
+            cp.messages
+                .iter()
+                .map(move |msg| format!("{}: {}", cp.line.label, msg))
+        })
+        .collect()
+}
 
-    Paragraph::new(Text::from(curr_not.to_string())).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Notifications"),
-    )
+/// Number of rows the notifications take when word wrapped to `width`.
+pub fn notifications_height(app: &App, routes_table_state: &RoutesTableState, width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    let mut height = 0;
+    for line in notification_lines(app, routes_table_state) {
+        let mut rows = 1;
+        let mut row_width = 0;
+        for word in line.split_whitespace() {
+            let word_width = word.width();
+            if row_width == 0 {
+                row_width = word_width;
+            } else if row_width + 1 + word_width <= width {
+                row_width += 1 + word_width;
+            } else {
+                rows += 1;
+                row_width = word_width;
+            }
+            // Words longer than a row are broken up
+            while row_width > width {
+                rows += 1;
+                row_width -= width;
+            }
+        }
+        height += rows;
+    }
+    height as u16
+}
+
+pub fn notifications<'a>(app: &'a App, routes_table_state: &RoutesTableState) -> Paragraph<'a> {
+    let text = notification_lines(app, routes_table_state).join("\n");
+
+    Paragraph::new(Text::from(text))
+        .wrap(Wrap { trim: true })
+        .scroll((routes_table_state.notifications_scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Notifications")
+                .border_style(pane_border(app, Focus::Notifications)),
+        )
 }
 
+/// The legs of the selected connection with their intermediate stops. Walks,
+/// e.g. from an address to the first station, are drawn dotted with their
+/// distance. The lines match `RoutesTableState::details_legs`.
 pub fn details_list<'a>(app: &'a App, routes_table_state: &RoutesTableState) -> List<'a> {
     let mut det = Vec::new();
-    if let Some(idx) = routes_table_state.table_state.selected() {
-        for j in &app.routes[idx].parts {
+    if let Some(conn) = routes_table_state.selected_route(app) {
+        for (leg, j) in conn.parts.iter().enumerate() {
+            let (top, bottom, walk) = match is_walk(j) {
+                true => (
+                    " ╭┄",
+                    " ╰┄",
+                    format!(" (walk, {:.0} m)", distance(&j.from, &j.to)),
+                ),
+                false => (" ╭─", " ╰─", String::new()),
+            };
             det.push(format!(
-                " ╭─ {}, {}",
+                "{} {}, {}",
+                top,
                 j.from.name,
-                j.from.planned_departure.format("%H:%M")
+                shown_time(&j.from.planned_departure, app.time_zone).format("%H:%M")
             ));
-            for k in &j.intermediate_stops {
-                // for l in k {
-                det.push(format!(
-                    " ├──── {}, {}",
-                    k.name,
-                    k.planned_departure.format("%H:%M")
-                ));
-                // }
+            if routes_table_state.collapsed_legs.contains(&leg) && !j.intermediate_stops.is_empty()
+            {
+                det.push(format!(" ├──── {} stops", j.intermediate_stops.len()));
+            } else {
+                for k in &j.intermediate_stops {
+                    det.push(format!(
+                        " ├──── {}, {}",
+                        k.name,
+                        shown_time(&k.planned_departure, app.time_zone).format("%H:%M")
+                    ));
+                }
             }
             det.push(format!(
-                " ╰─ {}, {}",
+                "{} {}, {}{}",
+                bottom,
                 j.to.name,
-                j.to.planned_departure.format("%H:%M")
+                shown_time(&j.to.planned_departure, app.time_zone).format("%H:%M"),
+                walk
             ));
         }
     }
@@ -153,5 +289,69 @@ pub fn details_list<'a>(app: &'a App, routes_table_state: &RoutesTableState) ->
         .iter()
         .map(|x| ListItem::new(Span::raw(x.clone())))
         .collect::<Vec<ListItem>>();
-    List::new(items).block(Block::default().borders(Borders::ALL).title("Details"))
+    let highlight_style = match (app.focus, app.input_mode) {
+        (Focus::Details, InputMode::Table) => Style::default().add_modifier(Modifier::REVERSED),
+        _ => Style::default(),
+    };
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Details")
+                .border_style(pane_border(app, Focus::Details)),
+        )
+        .highlight_style(highlight_style)
+}
+
+/// The whole trip over the via stops: each part with its lines and the time
+/// spent at the stops in between.
+pub fn plan_list(app: &App) -> List {
+    let mut parts = app
+        .routes
+        .iter()
+        .chain(&app.filtered_routes)
+        .collect::<Vec<&Connection>>();
+    parts.sort_by_key(|conn| departure(conn));
+
+    let mut lines = Vec::new();
+    for (i, conn) in parts.iter().enumerate() {
+        let (from, to) = (&conn.parts[0].from, &conn.parts[conn.parts.len() - 1].to);
+        lines.push(format!(
+            " {}  {}",
+            shown_time(&from.planned_departure, app.time_zone).format("%H:%M"),
+            from.name
+        ));
+        lines.push(format!(
+            "        {} ({} min)",
+            prepare_lines(&conn.parts),
+            (arrival(conn) - departure(conn)).num_minutes()
+        ));
+        lines.push(format!(
+            " {}  {}",
+            shown_time(&to.planned_departure, app.time_zone).format("%H:%M"),
+            to.name
+        ));
+        if let Some(next) = parts.get(i + 1) {
+            lines.push(format!(
+                "        {} min here",
+                (departure(next) - arrival(conn)).num_minutes()
+            ));
+        }
+    }
+    if let (Some(first), Some(last)) = (parts.first(), parts.last()) {
+        let total = (arrival(last) - departure(first)).num_minutes();
+        lines.push(String::new());
+        lines.push(format!(" Total {}:{:02}", total / 60, total % 60));
+    }
+
+    let items = lines
+        .into_iter()
+        .map(|line| ListItem::new(Span::raw(line)))
+        .collect::<Vec<ListItem>>();
+    List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Plan")
+            .border_style(pane_border(app, Focus::Details)),
+    )
 }
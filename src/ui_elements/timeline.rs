@@ -0,0 +1,144 @@
+use chrono::{DateTime, FixedOffset, Local};
+use mvg_api::routes::ConnectionPart;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::app::{shown_time, App, RoutesTableState};
+use crate::theme::Theme;
+
+pub fn line_color(label: &str) -> Color {
+    match label {
+        "U1" | "U7" => Color::Rgb(60, 115, 51),
+        "U2" | "U8" => Color::Rgb(195, 2, 45),
+        "U3" => Color::Rgb(237, 103, 32),
+        "U4" => Color::Rgb(0, 171, 133),
+        "U5" => Color::Rgb(188, 122, 0),
+        "U6" => Color::Rgb(0, 101, 175),
+        "S1" => Color::Rgb(22, 192, 233),
+        "S2" => Color::Rgb(113, 191, 68),
+        "S3" => Color::Rgb(123, 16, 125),
+        "S4" => Color::Rgb(238, 28, 37),
+        "S6" => Color::Rgb(0, 138, 81),
+        "S7" => Color::Rgb(150, 56, 51),
+        "S8" => Color::Rgb(255, 204, 0),
+        "S20" => Color::Rgb(240, 90, 115),
+        "FOOTWAY" => Color::Gray,
+        "12" | "16" | "17" | "18" | "19" | "20" | "21" | "23" | "25" | "27" | "28" | "29" => {
+            Color::Rgb(216, 32, 32)
+        }
+        _ => Color::Rgb(0, 88, 106),
+    }
+}
+
+fn minutes_between(from: &DateTime<Local>, to: &DateTime<Local>) -> i64 {
+    (*to - *from).num_minutes().max(0)
+}
+
+fn bar<'a>(offset: usize, length: usize, symbol: &str, style: Style, label: String) -> Spans<'a> {
+    Spans::from(vec![
+        Span::raw(" ".repeat(offset)),
+        Span::styled(symbol.repeat(length.max(1)), style),
+        Span::raw(format!(" {}", label)),
+    ])
+}
+
+fn boundary<'a>(
+    offset: usize,
+    time: &DateTime<Local>,
+    time_zone: Option<FixedOffset>,
+    name: &str,
+) -> Spans<'a> {
+    Spans::from(vec![
+        Span::raw(" ".repeat(offset)),
+        Span::styled(
+            format!("{} {}", shown_time(time, time_zone).format("%H:%M"), name),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ])
+}
+
+fn prepare_timeline<'a>(
+    parts: &[ConnectionPart],
+    width: u16,
+    theme: &Theme,
+    time_zone: Option<FixedOffset>,
+) -> Vec<Spans<'a>> {
+    let mut lines = Vec::new();
+    let (first, last) = match (parts.first(), parts.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return lines,
+    };
+    let start = first.from.planned_departure;
+    let total = minutes_between(&start, &last.to.planned_departure).max(1);
+    // Leave room for the borders and the label next to each bar
+    let scale = width.saturating_sub(12).max(1) as f64 / total as f64;
+    let column = |time: &DateTime<Local>| (minutes_between(&start, time) as f64 * scale) as usize;
+
+    let mut previous_arrival: Option<DateTime<Local>> = None;
+    for cp in parts.iter() {
+        let departure = cp.from.planned_departure;
+        let arrival = cp.to.planned_departure;
+
+        if let Some(prev) = previous_arrival {
+            let waiting = minutes_between(&prev, &departure);
+            if waiting > 0 {
+                let offset = column(&prev);
+                let length = column(&departure).saturating_sub(offset);
+                lines.push(bar(
+                    offset,
+                    length,
+                    "┄",
+                    Style::default().fg(theme.muted),
+                    format!("wait {}'", waiting),
+                ));
+            }
+        }
+
+        let offset = column(&departure);
+        let length = column(&arrival).saturating_sub(offset);
+        let duration = minutes_between(&departure, &arrival);
+        lines.push(boundary(offset, &departure, time_zone, &cp.from.name));
+        if cp.line.label == "FOOTWAY" {
+            lines.push(bar(
+                offset,
+                length,
+                "░",
+                Style::default().fg(line_color(&cp.line.label)),
+                format!("walk {}'", duration),
+            ));
+        } else {
+            lines.push(bar(
+                offset,
+                length,
+                "█",
+                Style::default().fg(line_color(&cp.line.label)),
+                format!("{} {}'", cp.line.label, duration),
+            ));
+        }
+        previous_arrival = Some(arrival);
+    }
+    lines.push(boundary(
+        column(&last.to.planned_departure),
+        &last.to.planned_departure,
+        time_zone,
+        &last.to.name,
+    ));
+    lines
+}
+
+pub fn timeline<'a>(
+    app: &'a App,
+    routes_table_state: &RoutesTableState,
+    width: u16,
+) -> Paragraph<'a> {
+    let lines = match routes_table_state.selected_route(app) {
+        Some(conn) => prepare_timeline(&conn.parts, width, &app.theme, app.time_zone),
+        None => Vec::new(),
+    };
+
+    Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("Timeline"))
+}
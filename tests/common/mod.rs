@@ -0,0 +1,23 @@
+//! Fixtures shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use mvg_api::routes::Connection;
+use mvg_tui::networking::{Disruption, NearbyStation};
+
+pub fn fixture_routes() -> Vec<Connection> {
+    serde_json::from_str(include_str!("../fixtures/connections.json")).unwrap()
+}
+
+/// A U3 ride, 5 minutes on foot and a bus leaving 7 minutes after the U3
+/// arrives.
+pub fn fixture_change_walk() -> Vec<Connection> {
+    serde_json::from_str(include_str!("../fixtures/change_walk.json")).unwrap()
+}
+
+pub fn fixture_disruptions() -> Vec<Disruption> {
+    serde_json::from_str(include_str!("../fixtures/disruptions.json")).unwrap()
+}
+
+pub fn fixture_nearby() -> Vec<NearbyStation> {
+    serde_json::from_str(include_str!("../fixtures/nearby.json")).unwrap()
+}
@@ -0,0 +1,545 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+use mvg_tui::app::{App, DetailsView, Focus, InputMode, RoutesTableState};
+use mvg_tui::itinerary::{parse_via, Stop};
+use mvg_tui::keymap::{Action, Keymap};
+use mvg_tui::messages::Severity;
+use mvg_tui::modes::transport_types;
+use mvg_tui::routes_view::SortKey;
+
+mod common;
+use common::{fixture_disruptions, fixture_nearby, fixture_routes};
+
+/// Feeds a sequence of keys to the app, as if they were typed in the terminal.
+fn press(app: &mut App, routes_table_state: &mut RoutesTableState, keys: &[KeyCode]) {
+    for code in keys {
+        app.handle_key(KeyEvent::new(*code, KeyModifiers::NONE), routes_table_state);
+    }
+}
+
+/// Like `press`, but every character of `keys` is one key press.
+fn type_keys(app: &mut App, routes_table_state: &mut RoutesTableState, keys: &str) {
+    let codes = keys.chars().map(KeyCode::Char).collect::<Vec<KeyCode>>();
+    press(app, routes_table_state, &codes);
+}
+
+fn app_with_focus(focus: Focus) -> App {
+    let mut app = App::default();
+    app.focus = focus;
+    app
+}
+
+#[test]
+fn focus_graph() {
+    // (from, h, j, k, l)
+    #[rustfmt::skip]
+    let graph = [
+        (Focus::Start,       Focus::Start,   Focus::Date,   Focus::Start,       Focus::Via),
+        (Focus::Via,         Focus::Start,   Focus::Mode(0), Focus::Via,        Focus::Destination),
+        (Focus::Destination, Focus::Via,     Focus::Mode(4), Focus::Destination, Focus::Destination),
+        (Focus::Date,        Focus::Date,    Focus::Routes, Focus::Start,       Focus::Time),
+        (Focus::Time,        Focus::Date,    Focus::Routes, Focus::Start,       Focus::Arrival),
+        (Focus::Arrival,     Focus::Time,    Focus::Routes, Focus::Start,       Focus::Mode(0)),
+        (Focus::Mode(0),     Focus::Arrival, Focus::Routes, Focus::Via,         Focus::Mode(1)),
+        (Focus::Mode(3),     Focus::Mode(2), Focus::Routes, Focus::Via,         Focus::Mode(4)),
+        (Focus::Mode(4),     Focus::Mode(3), Focus::Details, Focus::Destination, Focus::Mode(5)),
+        (Focus::Mode(6),     Focus::Mode(5), Focus::Details, Focus::Destination, Focus::Mode(6)),
+        (Focus::Routes,      Focus::Routes,  Focus::Notifications, Focus::Date,  Focus::Details),
+        (Focus::Details,     Focus::Routes,  Focus::Details, Focus::Mode(4),     Focus::Details),
+        (Focus::Notifications, Focus::Notifications, Focus::Notifications, Focus::Routes, Focus::Details),
+    ];
+    for (from, h, j, k, l) in graph {
+        for (keys, arrow, expected) in [
+            ("h", KeyCode::Left, h),
+            ("j", KeyCode::Down, j),
+            ("k", KeyCode::Up, k),
+            ("l", KeyCode::Right, l),
+        ] {
+            let mut app = app_with_focus(from);
+            type_keys(&mut app, &mut RoutesTableState::new(), keys);
+            assert_eq!(app.focus, expected, "{} from {:?}", keys, from);
+
+            let mut app = app_with_focus(from);
+            press(&mut app, &mut RoutesTableState::new(), &[arrow]);
+            assert_eq!(app.focus, expected, "{:?} from {:?}", arrow, from);
+        }
+    }
+}
+
+#[test]
+fn editing_start_and_destination() {
+    let mut app = App::default();
+    let mut state = RoutesTableState::new();
+
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert_eq!(app.input_mode, InputMode::Editing);
+    // hjkl and q are text while editing
+    type_keys(&mut app, &mut state, "Harrasq");
+    press(&mut app, &mut state, &[KeyCode::Backspace, KeyCode::Esc]);
+    assert_eq!(app.input_mode, InputMode::Normal);
+    assert_eq!(app.start, "Harras");
+    assert!(!app.should_quit);
+
+    type_keys(&mut app, &mut state, "lli");
+    type_keys(&mut app, &mut state, "Giesing");
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert_eq!(app.focus, Focus::Destination);
+    assert_eq!(app.input_destination, "Giesing");
+    assert_eq!(app.destination, "Giesing");
+}
+
+#[test]
+fn via_stops() {
+    let mut app = app_with_focus(Focus::Via);
+    let mut state = RoutesTableState::new();
+
+    type_keys(
+        &mut app,
+        &mut state,
+        "iOdeonsplatz +45; Garching, Forschungszentrum;",
+    );
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(!app.wrong_via);
+    assert_eq!(
+        app.via,
+        [
+            Stop {
+                station: "Odeonsplatz".to_string(),
+                stay_minutes: 45,
+            },
+            Stop {
+                station: "Garching, Forschungszentrum".to_string(),
+                stay_minutes: 0,
+            },
+        ]
+    );
+
+    type_keys(&mut app, &mut state, "i+x");
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(app.wrong_via);
+}
+
+#[test]
+fn trip_plan_view() {
+    let mut app = App::default();
+    let mut state = RoutesTableState::new();
+    let via = parse_via("Münchner Freiheit +20").unwrap();
+    let ids = |app: &App| app.routes.iter().map(|c| c.unique_id).collect::<Vec<_>>();
+    // Filter and sort of plain results leave the parts in the order of the trip
+    app.filter = "S8".to_string();
+    app.sort_descending = true;
+    app.set_plan(via, fixture_routes());
+    assert_eq!(ids(&app), vec![1, 2]);
+    assert!(app.filtered_routes.is_empty());
+    app.input_mode = InputMode::Table;
+    type_keys(&mut app, &mut state, "sS");
+    assert_eq!(ids(&app), vec![1, 2]);
+    app.input_mode = InputMode::Normal;
+    app.filter.clear();
+    app.sort_key = SortKey::Departure;
+    app.sort_descending = false;
+    assert_eq!(app.details_view, DetailsView::Plan);
+    // The plan is one of the details views until plain connections are fetched
+    type_keys(&mut app, &mut state, "t");
+    assert_eq!(app.details_view, DetailsView::List);
+    type_keys(&mut app, &mut state, "ttt");
+    assert_eq!(app.details_view, DetailsView::Plan);
+    app.set_routes(fixture_routes());
+    assert!(app.plan.is_empty());
+    assert_eq!(app.details_view, DetailsView::List);
+    type_keys(&mut app, &mut state, "ttt");
+    assert_eq!(app.details_view, DetailsView::List);
+}
+
+#[test]
+fn fetch_popup() {
+    // The popup is only shown while a request is on its way
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    let mut app = App::new(tx);
+    let mut state = RoutesTableState::new();
+    type_keys(&mut app, &mut state, "f");
+    assert!(app.show_fetch_popup);
+
+    // A second request does not fit into the full channel
+    app.show_fetch_popup = false;
+    type_keys(&mut app, &mut state, "f");
+    assert!(!app.show_fetch_popup);
+    assert_eq!(app.messages.last().unwrap().severity, Severity::Error);
+
+    assert!(rx.try_recv().is_ok());
+    type_keys(&mut app, &mut state, "f");
+    assert!(app.show_fetch_popup);
+}
+
+#[test]
+fn invalid_date_and_time() {
+    let mut app = app_with_focus(Focus::Date);
+    let mut state = RoutesTableState::new();
+
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    app.input_date.clear();
+    type_keys(&mut app, &mut state, "31.02.2023");
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(app.wrong_date);
+
+    // No fetch while the date is invalid
+    type_keys(&mut app, &mut state, "f");
+    assert!(!app.show_fetch_popup);
+
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    for _ in 0..10 {
+        press(&mut app, &mut state, &[KeyCode::Backspace]);
+    }
+    type_keys(&mut app, &mut state, "28.02.2023");
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert!(!app.wrong_date);
+    assert_eq!(app.datetime.format("%d.%m.%Y").to_string(), "28.02.2023");
+
+    type_keys(&mut app, &mut state, "li");
+    app.input_time.clear();
+    type_keys(&mut app, &mut state, "25:61");
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(app.wrong_time);
+}
+
+#[test]
+fn toggles() {
+    let mut app = app_with_focus(Focus::Arrival);
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "i");
+    assert!(app.is_arrival);
+    type_keys(&mut app, &mut state, "li");
+    assert_eq!(app.modes, [false, true, true, true, true, true, false]);
+    type_keys(&mut app, &mut state, "lilili");
+    assert_eq!(app.modes, [false, false, false, false, true, true, false]);
+    type_keys(&mut app, &mut state, "llli");
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert_eq!(app.focus, Focus::Mode(6));
+    assert_eq!(app.modes, [false, false, false, false, true, true, false]);
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert_eq!(
+        transport_types(&app.modes),
+        ["BAHN", "REGIONAL_BUS", "RUFTAXI"]
+    );
+    assert_eq!(app.input_mode, InputMode::Normal);
+}
+
+#[test]
+fn table_selection_wraps_around() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.routes = fixture_routes();
+    let mut state = RoutesTableState::new();
+
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert_eq!(app.input_mode, InputMode::Table);
+    type_keys(&mut app, &mut state, "j");
+    assert_eq!(state.table_state.selected(), Some(0));
+    type_keys(&mut app, &mut state, "j");
+    assert_eq!(state.table_state.selected(), Some(1));
+    press(&mut app, &mut state, &[KeyCode::Down]);
+    assert_eq!(state.table_state.selected(), Some(0));
+    press(&mut app, &mut state, &[KeyCode::Up]);
+    assert_eq!(state.table_state.selected(), Some(1));
+
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert_eq!(app.input_mode, InputMode::Normal);
+    assert_eq!(app.focus, Focus::Routes);
+    assert_eq!(state.table_state.selected(), Some(1));
+}
+
+#[test]
+fn table_navigation_without_routes() {
+    let mut app = app_with_focus(Focus::Routes);
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "ijkj");
+    assert_eq!(state.table_state.selected(), None);
+}
+
+#[test]
+fn details_scroll_and_collapse() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.routes = fixture_routes();
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "ijj");
+    assert_eq!(state.table_state.selected(), Some(1));
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    type_keys(&mut app, &mut state, "li");
+    assert_eq!(app.focus, Focus::Details);
+    // j/k move through the details lines, the routes selection stays
+    type_keys(&mut app, &mut state, "jjjjk");
+    assert_eq!(state.details_state.selected(), Some(2));
+    assert_eq!(state.table_state.selected(), Some(1));
+
+    // Line 2 is the start of the S8 leg, collapsing it hides its stops
+    let lines = state.details_legs(&app).len();
+    type_keys(&mut app, &mut state, "c");
+    assert!(state.collapsed_legs.contains(&1));
+    assert_eq!(state.details_legs(&app).len(), lines - 3);
+    assert_eq!(state.details_state.selected(), Some(2));
+    type_keys(&mut app, &mut state, "c");
+    assert!(state.collapsed_legs.is_empty());
+}
+
+#[test]
+fn pane_state_resets_with_selection() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.routes = fixture_routes();
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "ij");
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    type_keys(&mut app, &mut state, "jijj");
+    assert_eq!(app.focus, Focus::Notifications);
+    assert_eq!(state.notifications_scroll, 2);
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    type_keys(&mut app, &mut state, "kij");
+    assert_eq!(state.table_state.selected(), Some(1));
+    assert_eq!(state.notifications_scroll, 0);
+}
+
+#[test]
+fn maximize_pane() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.routes = fixture_routes();
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "z");
+    assert!(app.maximized);
+    // Still works while navigating the table, and the maximized pane follows the focus
+    type_keys(&mut app, &mut state, "ijz");
+    assert!(!app.maximized);
+    assert_eq!(state.table_state.selected(), Some(0));
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    type_keys(&mut app, &mut state, "zl");
+    assert!(app.maximized);
+    assert_eq!(app.focus, Focus::Details);
+}
+
+#[test]
+fn message_log() {
+    let mut app = app_with_focus(Focus::Start);
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "m");
+    assert!(app.show_log);
+    // Keys go to the log while it is open
+    type_keys(&mut app, &mut state, "lk");
+    assert_eq!(app.focus, Focus::Start);
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert!(!app.show_log);
+}
+
+fn first_lines(app: &App) -> Vec<String> {
+    app.routes
+        .iter()
+        .map(|conn| conn.parts.iter().map(|cp| cp.line.label.clone()).collect())
+        .collect::<Vec<Vec<String>>>()
+        .into_iter()
+        .map(|labels| labels.join(" "))
+        .collect()
+}
+
+#[test]
+fn sort_routes() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.set_routes(fixture_routes());
+    let mut state = RoutesTableState::new();
+    assert_eq!(first_lines(&app), ["U3 U6", "FOOTWAY S8 230"]);
+
+    // Departure -> arrival -> duration -> changes -> walking time
+    type_keys(&mut app, &mut state, "is");
+    assert_eq!(app.sort_key, SortKey::Arrival);
+    type_keys(&mut app, &mut state, "S");
+    assert_eq!(first_lines(&app), ["FOOTWAY S8 230", "U3 U6"]);
+    type_keys(&mut app, &mut state, "Ssss");
+    assert_eq!(app.sort_key, SortKey::Walking);
+    assert_eq!(first_lines(&app), ["U3 U6", "FOOTWAY S8 230"]);
+    type_keys(&mut app, &mut state, "S");
+    assert_eq!(first_lines(&app), ["FOOTWAY S8 230", "U3 U6"]);
+}
+
+#[test]
+fn selection_follows_filter() {
+    let mut app = app_with_focus(Focus::Routes);
+    app.set_routes(fixture_routes());
+    let mut state = RoutesTableState::new();
+    type_keys(&mut app, &mut state, "ijj");
+    assert_eq!(state.table_state.selected(), Some(1));
+    state.collapsed_legs.insert(0);
+
+    // Filtering down to one row moves the selection without a redraw
+    type_keys(&mut app, &mut state, "/u6");
+    assert_eq!(state.table_state.selected(), Some(0));
+    assert!(state.collapsed_legs.is_empty());
+
+    // A fetch with fewer rows does not panic before the next draw either
+    app.set_routes(Vec::new());
+    assert!(state.selected_route(&app).is_none());
+    assert!(state.details_legs(&app).is_empty());
+}
+
+#[test]
+fn filter_routes() {
+    let mut app = app_with_focus(Focus::Start);
+    app.set_routes(fixture_routes());
+    let mut state = RoutesTableState::new();
+
+    // The filter can be started from anywhere and ends in the table
+    type_keys(&mut app, &mut state, "/!u3");
+    assert!(app.editing_filter);
+    assert_eq!(first_lines(&app), ["FOOTWAY S8 230"]);
+    assert_eq!(app.filtered_routes.len(), 1);
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(!app.editing_filter);
+    assert_eq!(app.input_mode, InputMode::Table);
+    assert_eq!(app.focus, Focus::Routes);
+
+    // Station names match too, all words have to match
+    type_keys(&mut app, &mut state, "/");
+    for _ in 0..3 {
+        press(&mut app, &mut state, &[KeyCode::Backspace]);
+    }
+    type_keys(&mut app, &mut state, "garching münchner");
+    assert_eq!(first_lines(&app), ["U3 U6"]);
+
+    // New results are filtered the same way
+    app.set_routes(fixture_routes());
+    assert_eq!(first_lines(&app), ["U3 U6"]);
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert_eq!(app.filter, "garching münchner");
+}
+
+#[test]
+fn nearby_stations() {
+    let mut app = App::default();
+    let mut state = RoutesTableState::new();
+    app.set_nearby("Marienplatz".to_string(), fixture_nearby());
+    assert!(app.show_nearby);
+
+    // Keys of the overlay do not reach the inputs behind it
+    type_keys(&mut app, &mut state, "jjjjf");
+    assert_eq!(app.nearby_selected, 2);
+    assert!(!app.show_fetch_popup);
+
+    // The board goes back to the list
+    press(&mut app, &mut state, &[KeyCode::Enter]);
+    assert!(app.nearby_board);
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert!(!app.nearby_board);
+    assert!(app.show_nearby);
+
+    type_keys(&mut app, &mut state, "kd");
+    assert_eq!(app.destination, "Theatinerstraße");
+    assert_eq!(app.input_destination, "Theatinerstraße");
+    assert_eq!(app.focus, Focus::Destination);
+    assert!(!app.show_nearby);
+
+    app.set_nearby("here".to_string(), fixture_nearby());
+    type_keys(&mut app, &mut state, "s");
+    assert_eq!(app.start, "Marienplatz");
+    assert_eq!(app.focus, Focus::Start);
+    assert!(!app.show_nearby);
+}
+
+#[test]
+fn disruptions_overview() {
+    // The overview scrolls like the help and closes with the same keys
+    let mut app = App::default();
+    let mut state = RoutesTableState::new();
+    app.set_disruptions(fixture_disruptions());
+    type_keys(&mut app, &mut state, "jjk");
+    assert_eq!(app.disruptions_scroll, 1);
+    assert!(!app.show_fetch_popup);
+    press(&mut app, &mut state, &[KeyCode::Esc]);
+    assert!(!app.show_disruptions);
+}
+
+#[test]
+fn quit() {
+    let mut app = App::default();
+    type_keys(&mut app, &mut RoutesTableState::new(), "q");
+    assert!(app.should_quit);
+}
+
+#[test]
+fn help_overlay() {
+    let mut app = app_with_focus(Focus::Date);
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "?");
+    assert!(app.show_help);
+    // Navigation keys scroll the overlay instead of moving the focus
+    type_keys(&mut app, &mut state, "jjjk");
+    assert_eq!(app.help_scroll, 2);
+    assert_eq!(app.focus, Focus::Date);
+    type_keys(&mut app, &mut state, "q");
+    assert!(!app.show_help);
+    assert!(!app.should_quit);
+}
+
+#[test]
+fn configured_keymap() {
+    let config = BTreeMap::from([(
+        "normal".to_string(),
+        BTreeMap::from([
+            (Action::FocusLeft, vec!["d".to_string()]),
+            (
+                Action::FocusRight,
+                vec!["n".to_string(), "Right".to_string()],
+            ),
+        ]),
+    )]);
+    let mut app = app_with_focus(Focus::Time);
+    app.keymap = Keymap::from_config(&config).unwrap();
+    let mut state = RoutesTableState::new();
+
+    type_keys(&mut app, &mut state, "h");
+    assert_eq!(app.focus, Focus::Time);
+    type_keys(&mut app, &mut state, "d");
+    assert_eq!(app.focus, Focus::Date);
+    type_keys(&mut app, &mut state, "nn");
+    assert_eq!(app.focus, Focus::Arrival);
+    // Unconfigured actions keep their default keys
+    type_keys(&mut app, &mut state, "j");
+    assert_eq!(app.focus, Focus::Routes);
+
+    let config = BTreeMap::from([(
+        "normal".to_string(),
+        BTreeMap::from([(Action::Quit, vec!["Ctrl-q".to_string()])]),
+    )]);
+    assert!(Keymap::from_config(&config).is_err());
+
+    // A configured key is taken from the default action holding it
+    let config = BTreeMap::from([(
+        "table".to_string(),
+        BTreeMap::from([(Action::Copy, vec!["c".to_string()])]),
+    )]);
+    let keymap = Keymap::from_config(&config).unwrap();
+    assert_eq!(
+        keymap.action(InputMode::Table, KeyCode::Char('c')),
+        Some(Action::Copy)
+    );
+    assert_eq!(
+        keymap.keys(InputMode::Table, Action::ToggleStops),
+        &[KeyCode::Char(' ')]
+    );
+
+    // Two configured actions can not share a key
+    let config = BTreeMap::from([(
+        "table".to_string(),
+        BTreeMap::from([
+            (Action::Copy, vec!["c".to_string()]),
+            (Action::Sort, vec!["c".to_string()]),
+        ]),
+    )]);
+    let error = Keymap::from_config(&config).unwrap_err().to_string();
+    assert!(
+        error.contains("Copy") && error.contains("Sort"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use mvg_tui::app::App;
+use mvg_tui::messages::Severity;
+
+#[test]
+fn toasts_expire() {
+    let mut app = App::default();
+    let start = app.now;
+    for i in 0..5 {
+        app.log(Severity::Info, format!("Message {}", i));
+    }
+    let texts = app
+        .toasts()
+        .iter()
+        .map(|msg| msg.text.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(texts, ["Message 2", "Message 3", "Message 4"]);
+
+    app.now = start + chrono::Duration::from_std(Duration::from_secs(10)).unwrap();
+    assert!(app.toasts().is_empty());
+    assert_eq!(app.messages.len(), 5);
+}
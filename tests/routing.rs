@@ -0,0 +1,257 @@
+use clap::Parser;
+use mvg_api::routes::Connection;
+
+use mvg_tui::cli::{Cli, Command};
+use mvg_tui::disruptions::{affected_lines, by_line, plain_text, GENERAL};
+use mvg_tui::gps::{current_position, is_here, parse_report};
+use mvg_tui::itinerary::{parse_via, Stop};
+use mvg_tui::networking::parse_coordinates;
+use mvg_tui::routes_view::{Avoid, Preferences, WalkingSpeed};
+
+mod common;
+use common::{fixture_change_walk, fixture_disruptions, fixture_nearby, fixture_routes};
+
+#[test]
+fn avoid_lines_and_stations() {
+    let routes = fixture_routes();
+    let allowed = |avoid: &Avoid| routes.iter().filter(|conn| avoid.allows(conn)).count();
+    assert_eq!(allowed(&Avoid::default()), 2);
+
+    let mut avoid = Avoid {
+        lines: vec!["u6".to_string()],
+        ..Avoid::default()
+    };
+    assert_eq!(allowed(&avoid), 1);
+    // Walks are not a line that can be avoided
+    avoid.lines = vec!["FOOTWAY".to_string()];
+    assert_eq!(allowed(&avoid), 2);
+
+    // Passing through a station counts, not only changing there
+    avoid.lines.clear();
+    avoid.stations = vec!["Odeonsplatz".to_string()];
+    assert_eq!(allowed(&avoid), 1);
+    assert_eq!(avoid.describe(), "Odeonsplatz");
+    avoid.stations = vec!["Garching".to_string()];
+    assert_eq!(allowed(&avoid), 1);
+}
+
+#[test]
+fn routing_preferences() {
+    let mut routes = fixture_routes();
+    let allowed = |routes: &[Connection], preferences: &Preferences| {
+        routes
+            .iter()
+            .filter(|conn| preferences.allows(conn))
+            .count()
+    };
+    let mut preferences = Preferences::default();
+    assert_eq!(allowed(&routes, &preferences), 2);
+
+    // U3 -> U6 leaves 4 minutes, S8 -> 230 leaves 9
+    preferences.min_change_time = Some(5);
+    assert_eq!(allowed(&routes, &preferences), 1);
+    preferences.min_change_time = Some(10);
+    assert_eq!(allowed(&routes, &preferences), 0);
+    preferences.min_change_time = None;
+
+    // The walk at Marienplatz is about 38 m
+    preferences.max_walk = Some(30);
+    assert_eq!(allowed(&routes, &preferences), 1);
+    preferences.max_walk = Some(50);
+    assert_eq!(allowed(&routes, &preferences), 2);
+
+    // 5 minutes and 425 m on foot between the U3 and the bus, which leaves 7
+    // minutes after the U3 arrives. The walk is not slowed again if the API
+    // already took the walking speed into account.
+    let change = fixture_change_walk();
+    for (speed, kept) in [
+        (WalkingSpeed::Slow, 0),
+        (WalkingSpeed::Normal, 1),
+        (WalkingSpeed::Fast, 1),
+    ] {
+        let preferences = Preferences {
+            walking_speed: speed,
+            ..Preferences::default()
+        };
+        assert_eq!(allowed(&change, &preferences), kept, "{:?}", speed);
+    }
+    // The API walked slowly already, 7 minutes, and the bus leaves 9 minutes
+    // after the U3 arrives
+    let shift = chrono::Duration::from_std(std::time::Duration::from_secs(2 * 60)).unwrap();
+    let mut slowed = change.clone();
+    slowed[0].parts[1].to.planned_departure += shift;
+    slowed[0].parts[2].from.planned_departure += shift;
+    let slow = Preferences {
+        walking_speed: WalkingSpeed::Slow,
+        ..Preferences::default()
+    };
+    assert_eq!(allowed(&slowed, &slow), 1);
+
+    // 690 m as the crow flies, rounded up
+    assert_eq!(WalkingSpeed::Normal.walking_minutes(690), 9);
+    assert_eq!(WalkingSpeed::Slow.walking_minutes(690), 13);
+
+    // Only settings other than the defaults go into the query
+    assert!(Preferences::default().query().is_empty());
+    let careful = Preferences {
+        walking_speed: WalkingSpeed::Slow,
+        step_free: true,
+        max_walk: None,
+        min_change_time: Some(5),
+    };
+    assert_eq!(
+        careful.query(),
+        vec![
+            ("walkingSpeed".to_string(), "SLOW".to_string()),
+            ("changeTime".to_string(), "5".to_string()),
+        ]
+    );
+
+    preferences.step_free = true;
+    assert_eq!(allowed(&routes, &preferences), 2);
+    routes[1].parts[1]
+        .messages
+        .push("Aufzug am Marienplatz außer Betrieb".to_string());
+    assert_eq!(allowed(&routes, &preferences), 1);
+}
+
+#[test]
+fn coordinates_as_place() {
+    assert_eq!(
+        parse_coordinates("48.1374,11.5755"),
+        Some((48.1374, 11.5755))
+    );
+    assert_eq!(
+        parse_coordinates(" 48.1374 , 11.5755 "),
+        Some((48.1374, 11.5755))
+    );
+    // Station names with a comma are no coordinates
+    assert_eq!(parse_coordinates("Garching, Forschungszentrum"), None);
+    assert_eq!(parse_coordinates("148.1, 11.5"), None);
+    assert_eq!(parse_coordinates("48.1374"), None);
+}
+
+#[test]
+fn gpsd_reports() {
+    assert!(is_here("here"));
+    assert!(is_here(" Here "));
+    assert!(!is_here("Hermannstraße"));
+    assert_eq!(
+        parse_report(r#"{"class":"TPV","mode":3,"lat":48.1374,"lon":11.5755}"#),
+        Some((48.1374, 11.5755))
+    );
+    // No fix yet
+    assert_eq!(parse_report(r#"{"class":"TPV","mode":1}"#), None);
+    assert_eq!(
+        parse_report(r#"{"class":"SKY","mode":3,"lat":48.1,"lon":11.5}"#),
+        None
+    );
+    assert_eq!(parse_report("not json"), None);
+}
+
+/// A fake gpsd that answers the watch request with a version banner, a report
+/// without a fix and then a fix.
+#[tokio::test]
+async fn position_from_gpsd() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut watch = String::new();
+        BufReader::new(read).read_line(&mut watch).await.unwrap();
+        assert!(watch.starts_with("?WATCH="));
+        for line in [
+            r#"{"class":"VERSION","release":"3.25"}"#,
+            r#"{"class":"TPV","mode":1}"#,
+            r#"{"class":"TPV","mode":2,"lat":48.1506,"lon":11.5805}"#,
+        ] {
+            write
+                .write_all(format!("{}\n", line).as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+    assert_eq!(
+        current_position(&address).await.unwrap(),
+        (48.1506, 11.5805)
+    );
+}
+
+#[test]
+fn disruptions() {
+    let disruptions = fixture_disruptions();
+    let groups = by_line(&disruptions)
+        .into_iter()
+        .map(|(label, group)| (label, group.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(groups, vec![("S8", 1), ("U3", 1), ("U6", 2), (GENERAL, 1)]);
+
+    let routes = fixture_routes();
+    let affected = routes
+        .iter()
+        .map(|conn| affected_lines(conn, &disruptions))
+        .collect::<Vec<_>>();
+    // The S8 closure is weeks away
+    assert_eq!(affected, vec![vec!["U3", "U6"], vec![]]);
+
+    assert_eq!(
+        plain_text("<p>U6 f&auml;hrt <b>nicht</b></p><p>Bus &amp; Tram</p>"),
+        "U6 fährt nicht\nBus & Tram"
+    );
+    assert_eq!(plain_text("Erste<br/>Zweite <i"), "Erste\nZweite");
+}
+
+#[test]
+fn no_mode_flags() {
+    // `--no-bus` from before the list of modes still works next to `--no bus`
+    let cli = Cli::try_parse_from([
+        "mvg-tui",
+        "route",
+        "Marienplatz",
+        "Garching",
+        "--no-bus",
+        "--no",
+        "tram",
+    ])
+    .unwrap();
+    let args = match cli.command {
+        Some(Command::Route(args)) => args,
+        command => panic!("not a route: {:?}", command),
+    };
+    let keys = |modes: &[mvg_tui::modes::TransportMode]| {
+        modes.iter().map(|mode| mode.key).collect::<Vec<&str>>()
+    };
+    assert_eq!(keys(&args.no_flags.modes), vec!["bus"]);
+    assert_eq!(keys(&args.no_mode), vec!["tram"]);
+    assert!(Cli::try_parse_from(["mvg-tui", "route", "A", "B", "--no-boat"]).is_err());
+}
+
+#[test]
+fn nearby_station_lines() {
+    // Sorted and each line once, whatever the order of the departures
+    let nearby = fixture_nearby();
+    assert_eq!(nearby[0].lines(), vec!["S8", "U3", "U6"]);
+}
+
+#[test]
+fn via_parsing() {
+    assert_eq!(
+        parse_via("Odeonsplatz +45; Giesing").unwrap(),
+        [
+            Stop {
+                station: "Odeonsplatz".to_string(),
+                stay_minutes: 45,
+            },
+            Stop {
+                station: "Giesing".to_string(),
+                stay_minutes: 0,
+            },
+        ]
+    );
+    assert!(parse_via("Giesing +").is_err());
+    assert!(parse_via("+10").is_err());
+    assert_eq!(parse_via(" ; ").unwrap(), []);
+}
@@ -0,0 +1,411 @@
+use chrono::{DateTime, Local};
+use mvg_tui::{
+    app::{App, DetailsView, Focus, InputMode, RoutesTableState},
+    itinerary::parse_via,
+    messages::Severity,
+    modes::MODES,
+    routes_view::SortKey,
+    theme::Theme,
+    ui::{ui, LayoutMode},
+};
+use std::time::Duration;
+use tui::{
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    style::Style,
+    Terminal,
+};
+
+mod common;
+use common::{fixture_disruptions, fixture_nearby, fixture_routes};
+
+const WIDTH: u16 = 120;
+const HEIGHT: u16 = 36;
+
+fn fixed_app() -> App {
+    // Fixture times are in UTC, render them the same on every machine
+    let utc = DateTime::parse_from_rfc3339("2023-04-12T08:05:00Z").unwrap();
+    let now = utc.with_timezone(&Local);
+    let mut app = App::default();
+    app.time_zone = Some(*utc.offset());
+    app.now = now;
+    app.datetime = now;
+    app.input_start = "Marienplatz".to_string();
+    app.input_destination = "Garching, Forschungszentrum".to_string();
+    app.start = app.input_start.clone();
+    app.destination = app.input_destination.clone();
+    app.input_date = utc.format("%d.%m.%Y").to_string();
+    app.input_time = utc.format("%H:%M").to_string();
+    app.routes = fixture_routes();
+    app
+}
+
+/// Renders the buffer as its text, followed by the runs of cells that are not
+/// in the default style, so colour changes show up in the snapshots too.
+fn buffer_to_string(buffer: &Buffer) -> String {
+    let area = buffer.area();
+    let default_style = Cell::default().style();
+    let mut lines = Vec::new();
+    let mut styles = Vec::new();
+    for y in 0..area.height {
+        let mut line = String::new();
+        let mut run: Option<(u16, Style)> = None;
+        for x in 0..area.width {
+            let cell = buffer.get(x, y);
+            line.push_str(&cell.symbol);
+            let style = cell.style();
+            match run {
+                Some((_, current)) if current == style => {}
+                _ => {
+                    if let Some((start, current)) = run.take() {
+                        if current != default_style {
+                            styles.push(format!("{}: {}..{} {:?}", y, start, x, current));
+                        }
+                    }
+                    run = Some((x, style));
+                }
+            }
+        }
+        if let Some((start, current)) = run {
+            if current != default_style {
+                styles.push(format!("{}: {}..{} {:?}", y, start, area.width, current));
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    format!("{}\n\n{}", lines.join("\n"), styles.join("\n"))
+}
+
+fn render(app: &mut App, routes_table_state: &mut RoutesTableState) -> String {
+    render_sized(app, routes_table_state, WIDTH, HEIGHT)
+}
+
+fn render_sized(
+    app: &mut App,
+    routes_table_state: &mut RoutesTableState,
+    width: u16,
+    height: u16,
+) -> String {
+    let backend = TestBackend::new(width, height);
+    let mut terminal = Terminal::new(backend).unwrap();
+    terminal.draw(|f| ui(f, app, routes_table_state)).unwrap();
+    buffer_to_string(terminal.backend().buffer())
+}
+
+fn all_focus() -> Vec<Focus> {
+    let mut focus = vec![
+        Focus::Start,
+        Focus::Via,
+        Focus::Destination,
+        Focus::Date,
+        Focus::Time,
+        Focus::Arrival,
+        Focus::Routes,
+        Focus::Details,
+        Focus::Notifications,
+    ];
+    focus.extend((0..MODES.len()).map(Focus::Mode));
+    focus
+}
+
+/// Snapshot name of a focus, modes by their command line name.
+fn focus_name(focus: Focus) -> String {
+    match focus {
+        Focus::Mode(i) => MODES[i].key.to_string(),
+        focus => format!("{:?}", focus),
+    }
+}
+
+fn all_input_modes() -> Vec<InputMode> {
+    vec![InputMode::Normal, InputMode::Editing, InputMode::Table]
+}
+
+#[test]
+fn every_input_mode_and_focus() {
+    for input_mode in all_input_modes() {
+        for focus in all_focus() {
+            let name = format!("{:?}_{}", input_mode, focus_name(focus)).to_lowercase();
+            let mut routes_table_state = RoutesTableState::new();
+            if let InputMode::Table = input_mode {
+                routes_table_state.table_state.select(Some(0));
+            }
+            let mut app = fixed_app();
+            app.input_mode = input_mode;
+            app.focus = focus;
+            insta::assert_snapshot!(name, render(&mut app, &mut routes_table_state));
+        }
+    }
+}
+
+#[test]
+fn empty_app() {
+    let mut app = fixed_app();
+    app.routes = Vec::new();
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn selected_connection_with_walk() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(1));
+    let mut app = fixed_app();
+    app.input_mode = InputMode::Table;
+    app.focus = Focus::Routes;
+    insta::assert_snapshot!(render(&mut app, &mut routes_table_state));
+}
+
+#[test]
+fn disrupted_lines_badge() {
+    let mut app = fixed_app();
+    app.disruptions = fixture_disruptions();
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn disruptions_overview() {
+    let mut app = fixed_app();
+    app.set_disruptions(fixture_disruptions());
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn scrolled_and_collapsed_panes() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(0));
+    routes_table_state.details_state.select(Some(6));
+    routes_table_state.collapsed_legs.insert(0);
+    let mut app = fixed_app();
+    app.routes[0].parts[1].messages.push(
+        "Wegen einer Signalstörung kommt es zwischen Münchner Freiheit und Garching zu \
+         Verspätungen und Zugausfällen. Bitte planen Sie mehr Zeit ein."
+            .to_string(),
+    );
+    app.routes[0].parts[0]
+        .messages
+        .push("Aufzug am Marienplatz außer Betrieb".to_string());
+    app.input_mode = InputMode::Table;
+    app.focus = Focus::Details;
+    insta::assert_snapshot!(render(&mut app, &mut routes_table_state));
+
+    app.focus = Focus::Notifications;
+    routes_table_state.notifications_scroll = 1;
+    insta::assert_snapshot!(
+        "scrolled_notifications",
+        render(&mut app, &mut routes_table_state)
+    );
+}
+
+#[test]
+fn maximized_pane() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(1));
+    let mut app = fixed_app();
+    app.maximized = true;
+    app.focus = Focus::Details;
+    insta::assert_snapshot!(render(&mut app, &mut routes_table_state));
+
+    // Only the result panes can be maximized
+    app.focus = Focus::Date;
+    insta::assert_snapshot!(
+        "maximized_without_pane",
+        render(&mut app, &mut routes_table_state)
+    );
+}
+
+/// Logs messages at the given seconds before `app.now`.
+fn log_messages(app: &mut App, messages: &[(u64, Severity, &str)]) {
+    let now = app.now;
+    for (seconds_ago, severity, text) in messages {
+        app.now = now - chrono::Duration::from_std(Duration::from_secs(*seconds_ago)).unwrap();
+        app.log(*severity, *text);
+    }
+    app.now = now;
+}
+
+#[test]
+fn toasts_and_message_log() {
+    let mut app = fixed_app();
+    log_messages(
+        &mut app,
+        &[
+            (60, Severity::Info, "Fetched 2 connections in 380 ms"),
+            (
+                30,
+                Severity::Error,
+                "Could not copy to clipboard: not a terminal",
+            ),
+            (3, Severity::Warning, "Station not found: Marienplatzz"),
+            (1, Severity::Info, "Fetched 2 connections in 420 ms"),
+        ],
+    );
+    // Only the messages of the last few seconds are shown as toasts
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+
+    app.show_log = true;
+    app.log_scroll = u16::MAX;
+    insta::assert_snapshot!(
+        "message_log",
+        render(&mut app, &mut RoutesTableState::new())
+    );
+}
+
+#[test]
+fn sorted_and_filtered_routes() {
+    let mut routes_table_state = RoutesTableState::new();
+    let mut app = fixed_app();
+    let routes = std::mem::take(&mut app.routes);
+    app.sort_key = SortKey::Duration;
+    app.sort_descending = true;
+    app.filter = "!U6".to_string();
+    app.set_routes(routes);
+    app.focus = Focus::Routes;
+    app.input_mode = InputMode::Editing;
+    app.editing_filter = true;
+    insta::assert_snapshot!(render(&mut app, &mut routes_table_state));
+}
+
+#[test]
+fn trip_plan() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(0));
+    let mut app = fixed_app();
+    app.input_via = "Münchner Freiheit +20".to_string();
+    app.via = parse_via(&app.input_via).unwrap();
+    // Let the second connection leave 20 minutes after the first one arrives
+    let mut parts = fixture_routes();
+    let shift = chrono::Duration::from_std(Duration::from_secs(45 * 60)).unwrap();
+    for cp in &mut parts[1].parts {
+        for station in std::iter::once(&mut cp.from)
+            .chain(&mut cp.intermediate_stops)
+            .chain(std::iter::once(&mut cp.to))
+        {
+            station.planned_departure += shift;
+        }
+    }
+    app.set_plan(app.via.clone(), parts);
+    insta::assert_snapshot!(render(&mut app, &mut routes_table_state));
+}
+
+#[test]
+fn wrong_via_popup() {
+    let mut app = fixed_app();
+    app.input_via = "Giesing +x".to_string();
+    app.wrong_via = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn nearby_stations() {
+    let mut app = fixed_app();
+    app.set_nearby("Marienplatz".to_string(), fixture_nearby());
+    app.nearby_selected = 1;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn nearby_departure_board() {
+    // Minutes until departure count from `app.now` like the routes table
+    let mut app = fixed_app();
+    app.set_nearby("Marienplatz".to_string(), fixture_nearby());
+    app.nearby_board = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn narrow_layout() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(1));
+    let mut app = fixed_app();
+    app.focus = Focus::Mode(1);
+    app.modes[2] = false;
+    insta::assert_snapshot!(render_sized(&mut app, &mut routes_table_state, 80, 36));
+}
+
+#[test]
+fn minimal_layout() {
+    let mut routes_table_state = RoutesTableState::new();
+    routes_table_state.table_state.select(Some(1));
+    let mut app = fixed_app();
+    app.input_mode = InputMode::Table;
+    app.focus = Focus::Routes;
+    insta::assert_snapshot!(render_sized(&mut app, &mut routes_table_state, 60, 2));
+
+    let mut app = fixed_app();
+    app.input_mode = InputMode::Editing;
+    app.focus = Focus::Destination;
+    insta::assert_snapshot!(
+        "minimal_layout_editing",
+        render_sized(&mut app, &mut RoutesTableState::new(), 60, 2)
+    );
+}
+
+#[test]
+fn configured_layout() {
+    // A fixed layout is used regardless of the terminal size
+    let mut app = fixed_app();
+    app.layout = LayoutMode::Minimal;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn details_views() {
+    for details_view in [DetailsView::Timeline, DetailsView::Map] {
+        let name = format!("details_{:?}", details_view).to_lowercase();
+        let mut routes_table_state = RoutesTableState::new();
+        routes_table_state.table_state.select(Some(1));
+        let mut app = fixed_app();
+        app.input_mode = InputMode::Table;
+        app.focus = Focus::Routes;
+        app.details_view = details_view;
+        insta::assert_snapshot!(name, render(&mut app, &mut routes_table_state));
+    }
+}
+
+#[test]
+fn fetch_popup() {
+    let mut app = fixed_app();
+    app.show_fetch_popup = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn wrong_date_popup() {
+    let mut app = fixed_app();
+    app.input_date = "31.02.2023".to_string();
+    app.wrong_date = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn wrong_time_popup() {
+    let mut app = fixed_app();
+    app.input_time = "25:61".to_string();
+    app.wrong_time = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn help_overlay() {
+    let mut app = fixed_app();
+    app.show_help = true;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}
+
+#[test]
+fn help_overlay_scrolled_to_end() {
+    let mut app = fixed_app();
+    app.show_help = true;
+    // Scrolling past the end stops at the last line
+    app.help_scroll = 500;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+    assert!(app.help_scroll < 500);
+}
+
+#[test]
+fn colorblind_theme() {
+    let mut app = fixed_app();
+    app.theme = Theme::colorblind();
+    app.focus = Focus::Mode(0);
+    app.modes[1] = false;
+    insta::assert_snapshot!(render(&mut app, &mut RoutesTableState::new()));
+}